@@ -0,0 +1,108 @@
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    file_system::entry::FsEntry,
+    group::{Group, GroupTotals},
+};
+
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long ago a file was last modified, as shown in the age histogram.
+#[derive(Clone, Copy)]
+pub enum AgeBucket {
+    Day,
+    Week,
+    Month,
+    Year,
+    Older,
+}
+
+impl AgeBucket {
+    pub const ALL: [Self; 5] = [Self::Day, Self::Week, Self::Month, Self::Year, Self::Older];
+
+    pub fn of(modified: SystemTime, now: SystemTime) -> Self {
+        // Times in the future, as left behind by clock skew, count as recent
+        let age = now.duration_since(modified).unwrap_or_default();
+        if age <= DAY {
+            Self::Day
+        } else if age <= DAY * 7 {
+            Self::Week
+        } else if age <= DAY * 30 {
+            Self::Month
+        } else if age <= DAY * 365 {
+            Self::Year
+        } else {
+            Self::Older
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Day => "Last day",
+            Self::Week => "Last week",
+            Self::Month => "Last month",
+            Self::Year => "Last year",
+            Self::Older => "Older",
+        }
+    }
+}
+
+pub const UNKNOWN_AGE: &str = "(unknown)";
+
+/// Files totalled by how long before `now` they were last modified.
+pub struct AgeTotals {
+    now: SystemTime,
+    totals: GroupTotals,
+}
+
+impl AgeTotals {
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now,
+            totals: GroupTotals::default(),
+        }
+    }
+
+    /// Returns empty totals measured from the same time.
+    pub fn empty(&self) -> Self {
+        Self::new(self.now)
+    }
+
+    pub fn add(&mut self, fse: &FsEntry) {
+        if !matches!(fse, FsEntry::File { .. }) {
+            return;
+        }
+        let key = match fse.modified() {
+            Some(modified) => AgeBucket::of(modified, self.now).label(),
+            None => UNKNOWN_AGE,
+        };
+        self.totals.add(key, fse);
+    }
+
+    pub fn merge(&mut self, other: AgeTotals) {
+        self.totals.merge(other.totals);
+    }
+
+    /// Returns every bucket from the most recent to the oldest, including empty ones.
+    pub fn sorted(&self) -> Vec<Group> {
+        let mut groups = self.totals.sorted();
+        let mut take = |name: &str| {
+            groups
+                .iter()
+                .position(|g| g.name == name)
+                .map(|i| groups.remove(i))
+        };
+
+        let mut sorted: Vec<Group> = AgeBucket::ALL
+            .iter()
+            .map(|bucket| {
+                take(bucket.label()).unwrap_or_else(|| Group {
+                    name: bucket.label().to_string(),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        sorted.extend(take(UNKNOWN_AGE));
+        sorted
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use crossterm::{
+    cursor::MoveToColumn,
+    terminal::{Clear, ClearType},
+};
+
+use crate::{
+    age::AgeTotals,
+    config::Config,
+    duplicates::DuplicateFinder,
+    file_system::entry::FsEntry,
+    group::{extension_key, GroupTotals},
+    largest::LargestFiles,
+    owner::OwnerTotals,
+    stats::ScanStats,
+    units::system::UnitSystem,
+};
+
+/// The breakdowns of a scan that look at every file in the tree, rather than at the
+/// top-level entries. The readers total up the files of each top-level entry on their
+/// own, which are merged in once the filters have decided which entries are kept, so
+/// that a live scan and a loaded snapshot agree.
+pub struct Aggregates {
+    extensions: Option<GroupTotals>,
+    ages: Option<AgeTotals>,
+    owners: Option<OwnerTotals>,
+    largest: Option<LargestFiles>,
+    duplicates: Option<DuplicateFinder>,
+}
+
+impl Aggregates {
+    /// Paths are reported relative to `root`. Ages are measured from `now`.
+    pub fn new(config: &Config, root: &Path, now: SystemTime) -> Self {
+        Self {
+            extensions: config.by_ext.then(GroupTotals::default),
+            ages: config.by_age.then(|| AgeTotals::new(now)),
+            owners: config.by_owner.then(OwnerTotals::new),
+            largest: config
+                .largest
+                .map(|n| LargestFiles::new(n, root, config.unit_system == UnitSystem::Lines)),
+            duplicates: config.duplicates.then(|| DuplicateFinder::new(root)),
+        }
+    }
+
+    /// Returns empty breakdowns of the same kinds, for the files of a single entry.
+    pub fn empty(&self) -> Self {
+        Self {
+            extensions: self.extensions.as_ref().map(|_| GroupTotals::default()),
+            ages: self.ages.as_ref().map(AgeTotals::empty),
+            owners: self.owners.as_ref().map(OwnerTotals::empty),
+            largest: self.largest.as_ref().map(LargestFiles::empty),
+            duplicates: self.duplicates.as_ref().map(DuplicateFinder::empty),
+        }
+    }
+
+    /// Adds every file in a kept top-level entry, found at `path`.
+    pub fn add_entry(&mut self, fse: &FsEntry, path: &Path) {
+        match fse.children() {
+            Some(children) => {
+                for child in children {
+                    self.add_entry(child, &path.join(child.name()));
+                }
+            }
+            None => self.add_file(fse, path),
+        }
+    }
+
+    /// Adds a single file found at `path`. Other kinds of entries are skipped.
+    pub fn add_file(&mut self, fse: &FsEntry, path: &Path) {
+        if !matches!(fse, FsEntry::File { .. }) {
+            return;
+        }
+
+        if let Some(extensions) = &mut self.extensions {
+            extensions.add(&extension_key(fse.name()), fse);
+        }
+        if let Some(ages) = &mut self.ages {
+            ages.add(fse);
+        }
+        if let Some(owners) = &mut self.owners {
+            owners.add(fse);
+        }
+        if let Some(largest) = &mut self.largest {
+            largest.add(path, fse);
+        }
+        if let Some(duplicates) = &mut self.duplicates {
+            duplicates.add(path, fse);
+        }
+    }
+
+    pub fn merge(&mut self, other: Aggregates) {
+        if let (Some(extensions), Some(other)) = (&mut self.extensions, other.extensions) {
+            extensions.merge(other);
+        }
+        if let (Some(ages), Some(other)) = (&mut self.ages, other.ages) {
+            ages.merge(other);
+        }
+        if let (Some(owners), Some(other)) = (&mut self.owners, other.owners) {
+            owners.merge(other);
+        }
+        if let (Some(largest), Some(other)) = (&mut self.largest, other.largest) {
+            largest.merge(other);
+        }
+        if let (Some(duplicates), Some(other)) = (&mut self.duplicates, other.duplicates) {
+            duplicates.merge(other);
+        }
+    }
+
+    /// Moves the results into `stats`, comparing the contents of possible duplicates.
+    pub fn finish(self, stats: &mut ScanStats, errors: &mut Vec<anyhow::Error>) {
+        if let Some(extensions) = self.extensions {
+            stats.extensions = extensions.sorted();
+        }
+        if let Some(ages) = self.ages {
+            stats.ages = ages.sorted();
+        }
+        if let Some(owners) = self.owners {
+            (stats.users, stats.groups) = owners.sorted();
+        }
+        if let Some(largest) = self.largest {
+            stats.largest = largest.sorted();
+        }
+        if let Some(duplicates) = self.duplicates {
+            let mut stderr = io::stderr();
+
+            write!(stderr, "Comparing {} files...", duplicates.candidates()).unwrap();
+            stderr.flush().unwrap();
+
+            stats.duplicates = duplicates.find(errors);
+
+            crossterm::execute!(stderr, MoveToColumn(0), Clear(ClearType::CurrentLine)).unwrap();
+        }
+    }
+}
@@ -1,26 +1,41 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use anyhow::anyhow;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use regex::Regex;
 
 use crate::{
-    config::{Config, SortBy},
+    config::{BarScale, Config, OutputFormat, SortBy, TreeView},
     file_system::entry_type::EntryType,
-    filter::DirEntryFilter,
-    units::system::UnitSystem,
+    filter::{DirEntryFilter, SizeFilter},
+    units::{
+        parse::{parse_age, parse_size},
+        system::UnitSystem,
+    },
 };
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    #[arg(default_value = ".", help = "Root directory to scan")]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(
+        default_value = ".",
+        help = "Root directory to scan (a directory named diff is scanned as ./diff)"
+    )]
     pub dir: String,
 
     #[arg(
         name = "name",
         long = "name",
         short = 'n',
-        conflicts_with_all = ["size", "type"],
+        conflicts_with_all = ["size", "type", "sort"],
         help = "Sort entries by name"
     )]
     pub sort_by_name: bool,
@@ -29,7 +44,7 @@ pub struct Args {
         name = "size",
         long = "size",
         short = 's',
-        conflicts_with_all = ["name", "type"],
+        conflicts_with_all = ["name", "type", "sort"],
         help = "Sort entries by size"
     )]
     pub sort_by_size: bool,
@@ -38,23 +53,48 @@ pub struct Args {
         name = "type",
         long = "type",
         short = 't',
-        conflicts_with_all = ["name", "size"],
+        conflicts_with_all = ["name", "size", "sort"],
         help = "Sort entries by type"
     )]
     pub sort_by_type: bool,
 
+    #[arg(
+        name = "sort",
+        long = "sort",
+        value_enum,
+        help = "Sort entries by this key (mtime puts the most recently modified first)"
+    )]
+    pub sort_by: Option<SortBy>,
+
     #[arg(
         name = "reverse",
         long = "reverse",
         aliases = ["rev", "reversed"],
+        overrides_with = "no-reverse",
         help = "Reverse the sorting order"
     )]
     pub reverse: bool,
 
+    #[arg(
+        name = "no-reverse",
+        long = "no-reverse",
+        overrides_with = "reverse",
+        help = "Keep the sorting order, even when reversing it is set in a config file or DS_REVERSE"
+    )]
+    pub no_reverse: bool,
+
+    #[arg(
+        name = "raw",
+        long = "raw",
+        conflicts_with_all = ["si", "binary", "lines", "count"],
+        help = "Show sizes in bytes (the default, unless other units are set in a config file or DS_UNITS)"
+    )]
+    pub raw: bool,
+
     #[arg(
         name = "si",
         long = "si",
-        conflicts_with_all = ["binary", "lines"],
+        conflicts_with_all = ["binary", "lines", "count"],
         help = "Use SI (decimal) units for sizes (e.g., KB, MB)"
     )]
     pub si: bool,
@@ -64,7 +104,7 @@ pub struct Args {
         long = "binary",
         short = 'b',
         alias = "bin",
-        conflicts_with_all = ["si", "lines"],
+        conflicts_with_all = ["si", "lines", "count"],
         help = "Use binary (IEC) units for sizes (e.g., KiB, MiB)"
     )]
     pub binary: bool,
@@ -74,11 +114,61 @@ pub struct Args {
         long = "lines",
         short = 'l',
         alias = "plain-text",
-        conflicts_with_all = ["si", "binary"],
+        conflicts_with_all = ["si", "binary", "count"],
         help = "Count the number of lines in plain text files (non-plain text files are skipped)"
     )]
     pub lines: bool,
 
+    #[arg(
+        name = "count",
+        long = "count",
+        aliases = ["inodes", "count-files"],
+        conflicts_with_all = ["si", "binary", "lines", "languages", "largest", "duplicates"],
+        help = "Count the files and directories under each entry (like du --inodes) instead of measuring bytes"
+    )]
+    pub count: bool,
+
+    #[arg(
+        name = "disk-usage",
+        long = "disk-usage",
+        aliases = ["du", "allocated"],
+        help = "Measure allocated disk usage (like du) instead of apparent file size"
+    )]
+    pub disk_usage: bool,
+
+    #[arg(
+        name = "count-links",
+        long = "count-links",
+        help = "Count sizes of files with multiple hard links once per link"
+    )]
+    pub count_links: bool,
+
+    #[arg(
+        name = "one-file-system",
+        long = "one-file-system",
+        short = 'x',
+        help = "Skip directories on different filesystems than the root directory"
+    )]
+    pub one_file_system: bool,
+
+    #[arg(
+        name = "follow-links",
+        long = "follow-links",
+        short = 'L',
+        alias = "dereference",
+        help = "Follow symlinks, counting each linked directory and file only once"
+    )]
+    pub follow_links: bool,
+
+    #[arg(
+        name = "languages",
+        long = "languages",
+        aliases = ["langs", "lang"],
+        conflicts_with_all = ["si", "binary"],
+        help = "Count lines (like --lines) and break them down into code, comments and blanks per language"
+    )]
+    pub languages: bool,
+
     #[arg(
         name = "regex",
         long = "regex",
@@ -105,6 +195,22 @@ pub struct Args {
     )]
     pub exclude: Vec<String>,
 
+    #[arg(
+        name = "filter-recursive",
+        long = "filter-recursive",
+        aliases = ["recursive-filter", "deep-filter"],
+        help = "Apply name filters to nested entries too (directories only need to pass --exclude), and size/line filters to files only, at every depth"
+    )]
+    pub filter_recursive: bool,
+
+    #[arg(
+        name = "gitignore",
+        long = "gitignore",
+        aliases = ["git-ignore", "respect-ignore"],
+        help = "Skip entries matched by .gitignore, .ignore and .git/info/exclude rules"
+    )]
+    pub gitignore: bool,
+
     #[arg(
         name = "dirs-only",
         long = "dirs-only",
@@ -127,17 +233,45 @@ pub struct Args {
         name = "min-size",
         long = "min-size",
         alias = "min",
-        help = "Minimum file size to include (in bytes)"
+        help = "Minimum size in bytes to include, even in --lines mode (e.g., 4096, 500KB, 10MiB, 1.5GiB)"
     )]
-    pub min_size: Option<u64>,
+    pub min_size: Option<String>,
 
     #[arg(
         name = "max-size",
         long = "max-size",
         alias = "max",
-        help = "Maximum file size to include (in bytes)"
+        help = "Maximum size in bytes to include, even in --lines mode (e.g., 4096, 500KB, 10MiB, 1.5GiB)"
+    )]
+    pub max_size: Option<String>,
+
+    #[arg(
+        name = "min-lines",
+        long = "min-lines",
+        help = "Minimum number of lines to include (implies counting lines)"
+    )]
+    pub min_lines: Option<u64>,
+
+    #[arg(
+        name = "max-lines",
+        long = "max-lines",
+        help = "Maximum number of lines to include (implies counting lines)"
+    )]
+    pub max_lines: Option<u64>,
+
+    #[arg(
+        name = "older-than",
+        long = "older-than",
+        help = "Only include entries last modified longer ago than this (e.g., 90d, 12h, 2w, 1y)"
     )]
-    pub max_size: Option<u64>,
+    pub older_than: Option<String>,
+
+    #[arg(
+        name = "newer-than",
+        long = "newer-than",
+        help = "Only include entries modified more recently than this (e.g., 7d, 30m, 1w)"
+    )]
+    pub newer_than: Option<String>,
 
     #[arg(
         name = "max-bar-width",
@@ -148,11 +282,165 @@ pub struct Args {
     )]
     pub max_bar_width: u32,
 
+    #[arg(
+        name = "tree",
+        long = "tree",
+        help = "Show the full hierarchy of nested entries"
+    )]
+    pub tree: bool,
+
+    #[arg(
+        name = "depth",
+        long = "depth",
+        short = 'd',
+        alias = "max-depth",
+        help = "Show nested entries down to this depth (implies --tree)"
+    )]
+    pub depth: Option<usize>,
+
+    #[arg(
+        name = "scale-to-parent",
+        long = "scale-to-parent",
+        help = "Scale nested bars relative to their parent directory instead of the root"
+    )]
+    pub scale_to_parent: bool,
+
+    #[arg(
+        name = "by-ext",
+        long = "by-ext",
+        aliases = ["by-extension", "ext"],
+        conflicts_with_all = ["tree", "depth"],
+        help = "Show one bar per file extension across the whole tree"
+    )]
+    pub by_ext: bool,
+
+    #[arg(
+        name = "largest",
+        long = "largest",
+        aliases = ["top", "top-files"],
+        help = "Also show the N largest files at any depth, with their paths"
+    )]
+    pub largest: Option<usize>,
+
+    #[arg(
+        name = "duplicates",
+        long = "duplicates",
+        aliases = ["dupes", "dups"],
+        conflicts_with_all = [
+            "tree",
+            "depth",
+            "by-ext",
+            "by-age",
+            "by-owner",
+            "lines",
+            "languages",
+            "load"
+        ],
+        help = "Show sets of files with identical contents at any depth, ranked by the space wasted on copies"
+    )]
+    pub duplicates: bool,
+
+    #[arg(
+        name = "by-age",
+        long = "by-age",
+        aliases = ["age", "ages", "age-histogram"],
+        conflicts_with_all = ["tree", "depth", "by-ext", "by-owner", "duplicates"],
+        help = "Show how much of the tree was last modified in the past day, week, month, year or earlier"
+    )]
+    pub by_age: bool,
+
+    #[arg(
+        name = "by-owner",
+        long = "by-owner",
+        aliases = ["owner", "owners", "by-user"],
+        conflicts_with_all = ["tree", "depth", "by-ext", "by-age", "duplicates"],
+        help = "Show one bar per user and per group owning files across the whole tree"
+    )]
+    pub by_owner: bool,
+
+    #[arg(
+        name = "format",
+        long = "format",
+        short = 'f',
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format for the scan results"
+    )]
+    pub format: OutputFormat,
+
+    #[arg(
+        name = "interactive",
+        long = "interactive",
+        short = 'I',
+        aliases = ["tui", "browse"],
+        conflicts_with_all = [
+            "format",
+            "tree",
+            "depth",
+            "by-ext",
+            "by-age",
+            "by-owner",
+            "largest",
+            "duplicates"
+        ],
+        help = "Browse the scanned tree interactively, descending into directories"
+    )]
+    pub interactive: bool,
+
+    #[arg(
+        name = "trash-dir",
+        long = "trash-dir",
+        requires = "interactive",
+        help = "Directory that entries are moved to from the interactive view (defaults to the desktop trash, where they can be restored from)"
+    )]
+    pub trash_dir: Option<PathBuf>,
+
+    #[arg(
+        name = "save",
+        long = "save",
+        help = "Save the scanned tree, including sizes, lines, modification times and errors, to a JSON snapshot"
+    )]
+    pub save: Option<PathBuf>,
+
+    #[arg(
+        name = "load",
+        long = "load",
+        conflicts_with_all = [
+            "dir",
+            "gitignore",
+            "one-file-system",
+            "follow-links",
+            "count-links",
+            "languages",
+            "max-threads"
+        ],
+        help = "Show a snapshot saved with --save instead of scanning (sorting and filters still apply)"
+    )]
+    pub load: Option<PathBuf>,
+
+    #[arg(
+        name = "compare",
+        long = "compare",
+        conflicts_with_all = [
+            "interactive",
+            "by-ext",
+            "by-age",
+            "by-owner",
+            "largest",
+            "duplicates",
+            "languages",
+            "format"
+        ],
+        value_name = "FILE|DIR",
+        help = "Compare against a snapshot saved with --save, charting how much each entry grew or shrank. Given a directory, it is scanned too and shown side by side as the old (left) bars, with DIR as the new (right) ones"
+    )]
+    pub compare: Option<PathBuf>,
+
     #[arg(
         name = "max-threads",
         long = "max-threads",
         aliases = ["threads", "thread-cap"],
-        help = "Maximum number of scanner threads running in parallel"
+        help = "Number of scanner threads running in parallel (defaults to the number of CPUs)"
     )]
     pub max_threads: Option<usize>,
 
@@ -168,41 +456,154 @@ pub struct Args {
             "noerrs",
             "noerr"
         ],
+        overrides_with = "errors",
         help = "Suppress error messages like 'permission denied'"
     )]
     pub no_errors: bool,
+
+    #[arg(
+        name = "errors",
+        long = "errors",
+        overrides_with = "no-errors",
+        help = "Show error messages, even when they are suppressed in a config file or DS_NO_ERRORS"
+    )]
+    pub errors: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Compare two snapshots saved with --save instead of scanning (like --load NEW
+    /// --compare OLD), charting how much each entry grew or shrank
+    Diff {
+        #[arg(help = "Snapshot of the earlier scan")]
+        old: PathBuf,
+
+        #[arg(help = "Snapshot of the later scan")]
+        new: PathBuf,
+    },
+}
+
+impl Args {
+    /// Where the project config is looked up from: the scanned directory, or the one
+    /// holding the snapshot that is shown instead.
+    pub fn project_dir(&self) -> PathBuf {
+        let snapshot = match &self.command {
+            Some(Command::Diff { new, .. }) => Some(new),
+            None => self.load.as_ref(),
+        };
+        match snapshot {
+            Some(path) => match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+                _ => PathBuf::from("."),
+            },
+            None => PathBuf::from(&self.dir),
+        }
+    }
 }
 
 impl TryInto<Config> for Args {
     type Error = anyhow::Error;
 
     fn try_into(self) -> Result<Config, Self::Error> {
-        let actual_min = self.min_size.unwrap_or(0);
-        let actual_max = self.max_size.unwrap_or(u64::MAX);
-        if !(actual_min < actual_max) {
-            return Err(anyhow!(
-                "min_size must be less than max_size (got min_size: {}, max_size: {})",
-                actual_min,
-                actual_max
-            ));
-        }
-
         if let Some(n) = self.max_threads {
             if n == 0 {
                 return Err(anyhow!("max_threads must be greater than zero"));
             }
         }
 
+        if let Some(n) = self.depth {
+            if n == 0 {
+                return Err(anyhow!("depth must be greater than zero"));
+            }
+        }
+
+        if let Some(n) = self.largest {
+            if n == 0 {
+                return Err(anyhow!("largest must be greater than zero"));
+            }
+        }
+
+        // `ds diff OLD NEW` charts NEW the way --load does, compared against OLD
+        let (load, compare) = match self.command {
+            Some(Command::Diff { old, new }) => {
+                let conflicts = [
+                    (self.dir != ".", "a directory"),
+                    (self.load.is_some(), "--load"),
+                    (self.compare.is_some(), "--compare"),
+                    (self.interactive, "--interactive"),
+                    (self.by_ext, "--by-ext"),
+                    (self.by_age, "--by-age"),
+                    (self.by_owner, "--by-owner"),
+                    (self.largest.is_some(), "--largest"),
+                    (self.duplicates, "--duplicates"),
+                    (self.languages, "--languages"),
+                    (self.format != OutputFormat::Text, "--format"),
+                ];
+                if let Some((_, arg)) = conflicts.iter().find(|(conflicts, _)| *conflicts) {
+                    return Err(anyhow!("diff can't be combined with {arg}"));
+                }
+                (Some(new), Some(old))
+            }
+            None => (self.load, self.compare),
+        };
+
         let unit_system = if self.binary {
             UnitSystem::Binary
         } else if self.si {
             UnitSystem::SI
-        } else if self.lines {
+        } else if self.lines || self.languages {
             UnitSystem::Lines
+        } else if self.count {
+            UnitSystem::Count
         } else {
             UnitSystem::Raw
         };
 
+        let min_size = self.min_size.as_deref().map(parse_size).transpose()?;
+        let max_size = self.max_size.as_deref().map(parse_size).transpose()?;
+
+        let actual_min = min_size.unwrap_or(0);
+        let actual_max = max_size.unwrap_or(u64::MAX);
+        if actual_min >= actual_max {
+            return Err(anyhow!(
+                "min_size must be less than max_size (got min_size: {}, max_size: {})",
+                actual_min,
+                actual_max
+            ));
+        }
+
+        let actual_min = self.min_lines.unwrap_or(0);
+        let actual_max = self.max_lines.unwrap_or(u64::MAX);
+        if actual_min >= actual_max {
+            return Err(anyhow!(
+                "min_lines must be less than max_lines (got min_lines: {}, max_lines: {})",
+                actual_min,
+                actual_max
+            ));
+        }
+
+        let older_than = self.older_than.as_deref().map(parse_age).transpose()?;
+        let newer_than = self.newer_than.as_deref().map(parse_age).transpose()?;
+        if let (Some(older), Some(newer)) = (older_than, newer_than) {
+            if older >= newer {
+                return Err(anyhow!(
+                    "older_than must be less than newer_than (got older_than: {}, newer_than: {})",
+                    self.older_than.unwrap_or_default(),
+                    self.newer_than.unwrap_or_default()
+                ));
+            }
+        }
+
+        let now = SystemTime::now();
+        let size_filter = SizeFilter {
+            min_size,
+            max_size,
+            min_lines: self.min_lines,
+            max_lines: self.max_lines,
+            modified_before: older_than.and_then(|age| now.checked_sub(age)),
+            modified_after: newer_than.map(|age| now.checked_sub(age).unwrap_or(UNIX_EPOCH)),
+        };
+
         let sort_by = if self.sort_by_name {
             Some(SortBy::Name)
         } else if self.sort_by_size {
@@ -210,17 +611,17 @@ impl TryInto<Config> for Args {
         } else if self.sort_by_type {
             Some(SortBy::Type)
         } else {
-            None
+            self.sort_by
         };
 
         let filter = if let Some(regex_pattern) = self.regex {
             let re = Regex::new(&regex_pattern)?;
-            Some(DirEntryFilter::Regex(re))
+            Some(Arc::new(DirEntryFilter::Regex(re)))
         } else if !self.include.is_empty() || !self.exclude.is_empty() {
-            Some(DirEntryFilter::Glob {
+            Some(Arc::new(DirEntryFilter::Glob {
                 include: make_globset(&self.include)?,
                 exclude: make_globset(&self.exclude)?,
-            })
+            }))
         } else {
             None
         };
@@ -233,26 +634,57 @@ impl TryInto<Config> for Args {
             None
         };
 
+        let tree = if self.tree || self.depth.is_some() {
+            Some(TreeView {
+                max_depth: self.depth,
+                scale: if self.scale_to_parent {
+                    BarScale::Parent
+                } else {
+                    BarScale::Root
+                },
+            })
+        } else {
+            None
+        };
+
         Ok(Config {
             dir: self.dir,
             unit_system,
+            languages: self.languages,
             sort_by,
             filter,
+            filter_recursive: self.filter_recursive,
+            gitignore: self.gitignore,
             reverse: self.reverse,
             needs_type,
-            min_size: self.min_size,
-            max_size: self.max_size,
+            size_filter: (!size_filter.is_empty()).then_some(size_filter),
+            disk_usage: self.disk_usage,
+            count_links: self.count_links,
+            one_file_system: self.one_file_system,
+            follow_links: self.follow_links,
             max_bar_width: self.max_bar_width,
+            tree,
+            by_ext: self.by_ext,
+            by_age: self.by_age,
+            by_owner: self.by_owner,
+            largest: self.largest,
+            duplicates: self.duplicates,
+            format: self.format,
+            interactive: self.interactive,
+            trash_dir: self.trash_dir,
+            save: self.save,
+            load,
+            compare,
             max_threads: self.max_threads,
             no_errors: self.no_errors,
         })
     }
 }
 
-fn make_globset(patterns: &Vec<String>) -> Result<GlobSet, globset::Error> {
+fn make_globset(patterns: &[String]) -> Result<GlobSet, globset::Error> {
     let mut builder = GlobSetBuilder::new();
     for s in patterns {
-        builder.add(Glob::new(&s)?);
+        builder.add(Glob::new(s)?);
     }
     builder.build()
 }
@@ -1,22 +1,43 @@
-use std::ffi::OsString;
+use std::{ffi::OsString, path::PathBuf, sync::Arc};
 
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
 
 use crate::{
-    cli::Args, file_system::entry_type::EntryType, filter::DirEntryFilter,
+    cli::Args,
+    defaults::Defaults,
+    file_system::entry_type::EntryType,
+    filter::{DirEntryFilter, SizeFilter},
     units::system::UnitSystem,
 };
 
 pub struct Config {
     pub dir: String,
     pub unit_system: UnitSystem,
+    pub languages: bool,
     pub sort_by: Option<SortBy>,
     pub reverse: bool,
-    pub filter: Option<DirEntryFilter>,
+    pub filter: Option<Arc<DirEntryFilter>>,
+    pub filter_recursive: bool,
+    pub gitignore: bool,
     pub needs_type: Option<EntryType>,
-    pub min_size: Option<u64>,
-    pub max_size: Option<u64>,
+    pub size_filter: Option<SizeFilter>,
+    pub disk_usage: bool,
+    pub count_links: bool,
+    pub one_file_system: bool,
+    pub follow_links: bool,
     pub max_bar_width: u32,
+    pub tree: Option<TreeView>,
+    pub by_ext: bool,
+    pub by_age: bool,
+    pub by_owner: bool,
+    pub largest: Option<usize>,
+    pub duplicates: bool,
+    pub format: OutputFormat,
+    pub interactive: bool,
+    pub trash_dir: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub load: Option<PathBuf>,
+    pub compare: Option<PathBuf>,
     pub max_threads: Option<usize>,
     pub no_errors: bool,
 }
@@ -27,13 +48,59 @@ impl Config {
         I: IntoIterator<Item = T>,
         T: Into<OsString> + Clone,
     {
-        Args::try_parse_from(itr)?.try_into()
+        let matches = Args::command().try_get_matches_from(itr)?;
+        let mut args = Args::from_arg_matches(&matches)?;
+        Defaults::load(&args.project_dir())?.apply(&mut args, &matches)?;
+        args.try_into()
+    }
+
+    /// Whether nested entries are needed after the scan, rather than just their totals.
+    pub fn keep_children(&self) -> bool {
+        self.tree.is_some() || self.interactive || self.save.is_some() || self.compare.is_some()
+    }
+
+    /// How deep nested entries are shown. They are only shown in tree view, even when
+    /// they were kept for --save.
+    pub fn max_depth(&self) -> Option<usize> {
+        match &self.tree {
+            Some(tree) => tree.max_depth,
+            None => Some(1),
+        }
+    }
+
+    /// Whether nested files are totalled up or ranked, which the readers do for each
+    /// top-level entry so that the tree itself can be dropped as it is read.
+    pub fn aggregates_files(&self) -> bool {
+        self.by_ext || self.by_age || self.by_owner || self.largest.is_some() || self.duplicates
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
 pub enum SortBy {
     Name,
     Size,
     Type,
+    #[value(name = "mtime", alias = "modified")]
+    Modified,
+}
+
+#[derive(Clone, Copy)]
+pub struct TreeView {
+    pub max_depth: Option<usize>,
+    pub scale: BarScale,
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum BarScale {
+    Root,
+    Parent,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Csv,
+    Tsv,
 }
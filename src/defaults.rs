@@ -0,0 +1,337 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use clap::{parser::ValueSource, ArgMatches, ValueEnum};
+use serde::Deserialize;
+
+use crate::{cli::Args, config::SortBy, units::system::UnitSystem};
+
+const PROJECT_FILE: &str = ".ds.toml";
+
+/// Option defaults read from config files and `DS_*` environment variables, which fill
+/// in whatever wasn't passed on the command line.
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Defaults {
+    units: Option<String>,
+    sort: Option<String>,
+    reverse: Option<bool>,
+    max_bar_width: Option<u32>,
+    max_threads: Option<usize>,
+    exclude: Option<Vec<String>>,
+    no_errors: Option<bool>,
+}
+
+impl Defaults {
+    /// Layers the user config, then the project config nearest to `dir`, then the
+    /// environment, each one overriding the keys set by the ones before it.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        Self::load_layers(user_config_path().as_deref(), dir, Self::from_env()?)
+    }
+
+    fn load_layers(user_config: Option<&Path>, dir: &Path, env: Self) -> anyhow::Result<Self> {
+        let mut defaults = Self::default();
+
+        if let Some(path) = user_config {
+            defaults = defaults.merge(Self::read(path)?);
+        }
+        // Relative paths like "." have no ancestors to search until they are resolved
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if let Some(path) = find_project_config(&dir) {
+            defaults = defaults.merge(Self::read(&path)?);
+        }
+
+        Ok(defaults.merge(env))
+    }
+
+    fn read(path: &Path) -> anyhow::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => {
+                return Err(anyhow!(
+                    "error reading config '{}': {err}",
+                    path.to_string_lossy()
+                ))
+            }
+        };
+
+        toml::from_str(&contents)
+            .map_err(|err| anyhow!("error parsing config '{}': {err}", path.to_string_lossy()))
+    }
+
+    fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(env_var)
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        Ok(Self {
+            units: var("DS_UNITS"),
+            sort: var("DS_SORT"),
+            reverse: var("DS_REVERSE")
+                .map(|s| parse_bool("DS_REVERSE", &s))
+                .transpose()?,
+            max_bar_width: var("DS_MAX_BAR_WIDTH")
+                .map(|s| parse_number("DS_MAX_BAR_WIDTH", &s))
+                .transpose()?,
+            max_threads: var("DS_MAX_THREADS")
+                .map(|s| parse_number("DS_MAX_THREADS", &s))
+                .transpose()?,
+            exclude: var("DS_EXCLUDE").map(|s| split_patterns(&s)),
+            no_errors: var("DS_NO_ERRORS")
+                .map(|s| parse_bool("DS_NO_ERRORS", &s))
+                .transpose()?,
+        })
+    }
+
+    fn merge(self, other: Self) -> Self {
+        Self {
+            units: other.units.or(self.units),
+            sort: other.sort.or(self.sort),
+            reverse: other.reverse.or(self.reverse),
+            max_bar_width: other.max_bar_width.or(self.max_bar_width),
+            max_threads: other.max_threads.or(self.max_threads),
+            exclude: other.exclude.or(self.exclude),
+            no_errors: other.no_errors.or(self.no_errors),
+        }
+    }
+
+    /// Fills in the options that `matches` didn't get from the command line.
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) -> anyhow::Result<()> {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        if let Some(units) = &self.units {
+            let has_units = ["raw", "si", "binary", "lines", "count", "languages"]
+                .iter()
+                .any(|id| from_cli(id));
+            if !has_units {
+                // Modes that only make sense for bytes keep them, as they would on the CLI
+                let is_bytes_only = args.duplicates || args.largest.is_some();
+                match parse_units(units)? {
+                    UnitSystem::Raw => {}
+                    UnitSystem::SI => args.si = true,
+                    UnitSystem::Binary => args.binary = true,
+                    UnitSystem::Lines if !args.duplicates => args.lines = true,
+                    UnitSystem::Count if !is_bytes_only => args.count = true,
+                    UnitSystem::Lines | UnitSystem::Count => {}
+                }
+            }
+        }
+
+        if let Some(sort) = &self.sort {
+            let has_sort = ["name", "size", "type", "sort"]
+                .iter()
+                .any(|id| from_cli(id));
+            if !has_sort {
+                args.sort_by = Some(
+                    SortBy::from_str(sort, true)
+                        .map_err(|_| anyhow!("invalid sort order '{sort}' in defaults"))?,
+                );
+            }
+        }
+
+        if let (Some(reverse), false) =
+            (self.reverse, from_cli("reverse") || from_cli("no-reverse"))
+        {
+            args.reverse = reverse;
+        }
+        if let (Some(width), false) = (self.max_bar_width, from_cli("max-bar-width")) {
+            args.max_bar_width = width;
+        }
+        if let (Some(n), false) = (self.max_threads, from_cli("max-threads")) {
+            args.max_threads = Some(n);
+        }
+        // Glob patterns can't be combined with a regex, which takes over from them
+        if let (Some(exclude), false) = (self.exclude, from_cli("exclude") || from_cli("regex")) {
+            args.exclude = exclude;
+        }
+        if let (Some(no_errors), false) =
+            (self.no_errors, from_cli("no-errors") || from_cli("errors"))
+        {
+            args.no_errors = no_errors;
+        }
+
+        Ok(())
+    }
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("ds/config.toml"))
+}
+
+/// The project config in `dir` or the closest directory above it.
+fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Splits comma-separated glob patterns, leaving alone the commas of `{a,b}`
+/// alternatives and escaped ones.
+fn split_patterns(s: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut pattern = String::new();
+    let mut depth = 0usize;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                pattern.push(c);
+                pattern.extend(chars.next());
+                continue;
+            }
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                patterns.push(std::mem::take(&mut pattern));
+                continue;
+            }
+            _ => {}
+        }
+        pattern.push(c);
+    }
+    patterns.push(pattern);
+
+    patterns
+        .iter()
+        .map(|pattern| pattern.trim())
+        .filter(|pattern| !pattern.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok()
+}
+
+fn parse_units(s: &str) -> anyhow::Result<UnitSystem> {
+    [
+        UnitSystem::Raw,
+        UnitSystem::SI,
+        UnitSystem::Binary,
+        UnitSystem::Lines,
+        UnitSystem::Count,
+    ]
+    .into_iter()
+    .find(|unit_system| unit_system.name().eq_ignore_ascii_case(s))
+    .ok_or_else(|| {
+        anyhow!("invalid units '{s}' in defaults (expected raw, si, binary, lines or count)")
+    })
+}
+
+fn parse_bool(name: &str, s: &str) -> anyhow::Result<bool> {
+    match s.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => Err(anyhow!(
+            "invalid value '{s}' for {name}: expected true or false"
+        )),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, s: &str) -> anyhow::Result<T> {
+    s.trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid value '{s}' for {name}: expected a number"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Defaults {
+        let vars: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Defaults::from_vars(|name| vars.get(name).cloned()).unwrap()
+    }
+
+    fn parse_with(defaults: Defaults, cli: &[&str]) -> Args {
+        let matches = Args::command()
+            .try_get_matches_from(["ds"].iter().chain(cli))
+            .unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        defaults.apply(&mut args, &matches).unwrap();
+        args
+    }
+
+    #[test]
+    fn layers_user_project_env_then_cli() {
+        let root = env::temp_dir().join(format!("ds-defaults-test-{}", std::process::id()));
+        let scanned = root.join("project/src");
+        fs::create_dir_all(&scanned).unwrap();
+        let user_config = root.join("config.toml");
+        fs::write(
+            &user_config,
+            "units = \"si\"\nsort = \"name\"\nreverse = true\nmax_bar_width = 10\nmax_threads = 2\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("project").join(PROJECT_FILE),
+            "units = \"binary\"\nmax_bar_width = 20\nmax_threads = 3\n",
+        )
+        .unwrap();
+
+        let env = vars(&[("DS_MAX_BAR_WIDTH", "30"), ("DS_SORT", "size")]);
+        let defaults = Defaults::load_layers(Some(&user_config), &scanned, env).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(defaults.reverse, Some(true)); // user
+        assert_eq!(defaults.units.as_deref(), Some("binary")); // project
+        assert_eq!(defaults.max_threads, Some(3)); // project
+        assert_eq!(defaults.sort.as_deref(), Some("size")); // env
+        assert_eq!(defaults.max_bar_width, Some(30)); // env
+
+        let args = parse_with(defaults, &["--max-bar-width", "40"]);
+        assert_eq!(args.max_bar_width, 40);
+        assert_eq!(args.max_threads, Some(3));
+        assert_eq!(args.sort_by, Some(SortBy::Size));
+        assert!(args.binary && args.reverse);
+    }
+
+    #[test]
+    fn cli_flags_undo_defaults() {
+        let defaults = || {
+            vars(&[
+                ("DS_UNITS", "binary"),
+                ("DS_REVERSE", "true"),
+                ("DS_NO_ERRORS", "1"),
+            ])
+        };
+
+        let args = parse_with(defaults(), &[]);
+        assert!(args.binary && args.reverse && args.no_errors);
+
+        let args = parse_with(defaults(), &["--raw", "--no-reverse", "--errors"]);
+        assert!(!args.binary && !args.reverse && !args.no_errors);
+
+        // The last of a pair of opposite flags wins
+        let args = parse_with(defaults(), &["--no-reverse", "--reverse"]);
+        assert!(args.reverse);
+    }
+
+    #[test]
+    fn splits_patterns() {
+        assert_eq!(split_patterns("target, *.log,,"), ["target", "*.log"]);
+        assert_eq!(
+            split_patterns("*.{jpg,png},node_modules"),
+            ["*.{jpg,png}", "node_modules"]
+        );
+        assert_eq!(split_patterns("a\\,b,c"), ["a\\,b", "c"]);
+        assert_eq!(split_patterns("{a,{b,c}},d"), ["{a,{b,c}}", "d"]);
+        assert!(split_patterns("").is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use crate::{config::SortBy, file_system::entry::FsEntry, units::system::UnitSystem};
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Change {
+    Grown,
+    Shrunk,
+    Unchanged,
+    New,
+    Deleted,
+}
+
+/// An entry matched by path between an earlier and a later scan.
+pub struct DiffRow {
+    pub path: PathBuf,
+    pub old: Option<u64>,
+    pub new: Option<u64>,
+}
+
+impl DiffRow {
+    pub fn delta(&self) -> i128 {
+        self.new.unwrap_or(0) as i128 - self.old.unwrap_or(0) as i128
+    }
+
+    pub fn change(&self) -> Change {
+        match (self.old, self.new) {
+            (None, _) => Change::New,
+            (_, None) => Change::Deleted,
+            (Some(old), Some(new)) if new > old => Change::Grown,
+            (Some(old), Some(new)) if new < old => Change::Shrunk,
+            _ => Change::Unchanged,
+        }
+    }
+}
+
+/// Matches entries by path down to `max_depth` (all the way when `None`), measuring them
+/// in the chosen unit system. Entries that only exist on one side are not descended into.
+pub fn diff_entries(
+    old: &[FsEntry],
+    new: &[FsEntry],
+    unit_system: &UnitSystem,
+    max_depth: Option<usize>,
+) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    diff_level(
+        old,
+        new,
+        unit_system,
+        max_depth,
+        Path::new(""),
+        1,
+        &mut rows,
+    );
+    rows
+}
+
+fn diff_level(
+    old: &[FsEntry],
+    new: &[FsEntry],
+    unit_system: &UnitSystem,
+    max_depth: Option<usize>,
+    prefix: &Path,
+    depth: usize,
+    rows: &mut Vec<DiffRow>,
+) {
+    let units = |fse: &FsEntry| unit_system.units_of(fse).unwrap_or(0);
+
+    let mut old_by_name: HashMap<&OsString, &FsEntry> =
+        old.iter().map(|fse| (fse.name(), fse)).collect();
+
+    for new_fse in new {
+        let path = prefix.join(new_fse.name());
+        let old_fse = old_by_name.remove(new_fse.name());
+
+        if let (Some(old_fse), false) = (old_fse, max_depth.is_some_and(|max| depth >= max)) {
+            if let (Some(old_children), Some(new_children)) =
+                (old_fse.children(), new_fse.children())
+            {
+                diff_level(
+                    old_children,
+                    new_children,
+                    unit_system,
+                    max_depth,
+                    &path,
+                    depth + 1,
+                    rows,
+                );
+            }
+        }
+
+        rows.push(DiffRow {
+            path,
+            old: old_fse.map(units),
+            new: Some(units(new_fse)),
+        });
+    }
+
+    // Whatever is left was not found in the later scan
+    for old_fse in old {
+        if old_by_name.contains_key(old_fse.name()) {
+            rows.push(DiffRow {
+                path: prefix.join(old_fse.name()),
+                old: Some(units(old_fse)),
+                new: None,
+            });
+        }
+    }
+}
+
+/// Orders rows by the size of their change, biggest first, unless sorting by name or type.
+pub fn sort_diff_rows(rows: &mut [DiffRow], sort_by: Option<SortBy>, reverse: bool) {
+    match sort_by {
+        Some(SortBy::Name) => rows.sort_by(|a, b| a.path.cmp(&b.path)),
+        Some(SortBy::Type) => rows.sort_by_key(|row| row.change() as u8),
+        // Rows carry no times, so they fall back to the size of their change
+        Some(SortBy::Size) | Some(SortBy::Modified) | None => {
+            rows.sort_by_key(|row| Reverse(row.delta().unsigned_abs()))
+        }
+    }
+    if reverse {
+        rows.reverse();
+    }
+}
@@ -0,0 +1,240 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fs::File,
+    hash::Hasher,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+
+use crate::file_system::entry::FsEntry;
+
+// Files that share a size are first told apart by a hash of their beginning, which is
+// usually enough to rule out everything but real copies without reading them in full
+const PARTIAL_HASH_LEN: u64 = 4096;
+
+/// Files with identical contents, named by their paths relative to the scanned directory.
+pub struct DuplicateGroup {
+    pub size: u64,
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// The space taken up by every copy but one.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Files grouped by size as they are added. Only files that share a size with another
+/// one are read again to compare their contents.
+pub struct DuplicateFinder {
+    root: PathBuf,
+    // Keyed by apparent size, with the measured size of each file
+    by_size: HashMap<u64, Vec<(PathBuf, u64)>>,
+}
+
+impl DuplicateFinder {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            by_size: HashMap::new(),
+        }
+    }
+
+    pub fn empty(&self) -> Self {
+        Self::new(&self.root)
+    }
+
+    pub fn add(&mut self, path: &Path, fse: &FsEntry) {
+        let FsEntry::File {
+            size,
+            apparent_size,
+            ..
+        } = fse
+        else {
+            return;
+        };
+        // Empty files waste no space, and extra hard links are measured as empty
+        if *apparent_size == 0 {
+            return;
+        }
+
+        self.by_size
+            .entry(*apparent_size)
+            .or_default()
+            .push((path.to_path_buf(), *size));
+    }
+
+    pub fn merge(&mut self, other: DuplicateFinder) {
+        for (apparent_size, mut files) in other.by_size {
+            self.by_size
+                .entry(apparent_size)
+                .or_default()
+                .append(&mut files);
+        }
+    }
+
+    /// The number of files that share their size with at least one other file.
+    pub fn candidates(&self) -> usize {
+        self.by_size
+            .values()
+            .filter(|files| files.len() > 1)
+            .map(|files| files.len())
+            .sum()
+    }
+
+    /// Compares the contents of files of the same size, returning each set of copies
+    /// with the most wasted space first. Files that can't be read are left out.
+    pub fn find(self, errors: &mut Vec<anyhow::Error>) -> Vec<DuplicateGroup> {
+        let mut groups = Vec::new();
+
+        for (apparent_size, files) in self.by_size {
+            if files.len() < 2 {
+                continue;
+            }
+
+            for partial_matches in group_by_hash(files, Some(PARTIAL_HASH_LEN), errors) {
+                // The partial hash already covered the whole of small files
+                let full_matches = if apparent_size <= PARTIAL_HASH_LEN {
+                    vec![partial_matches]
+                } else {
+                    group_by_hash(partial_matches, None, errors)
+                };
+
+                // Hashes only rule files out, so copies are confirmed byte for byte
+                let copies = full_matches
+                    .into_iter()
+                    .flat_map(|files| group_by_contents(files, errors));
+
+                for mut files in copies {
+                    files.sort();
+                    groups.push(DuplicateGroup {
+                        size: files[0].1,
+                        paths: files
+                            .into_iter()
+                            .map(|(path, _)| {
+                                path.strip_prefix(&self.root).unwrap_or(&path).to_path_buf()
+                            })
+                            .collect(),
+                    });
+                }
+            }
+        }
+
+        groups.sort_by(|a, b| {
+            b.wasted()
+                .cmp(&a.wasted())
+                .then_with(|| a.paths.cmp(&b.paths))
+        });
+        groups
+    }
+}
+
+/// Splits files into sets with matching hashes, dropping sets of a single file.
+fn group_by_hash(
+    files: Vec<(PathBuf, u64)>,
+    limit: Option<u64>,
+    errors: &mut Vec<anyhow::Error>,
+) -> Vec<Vec<(PathBuf, u64)>> {
+    let mut by_hash: HashMap<u64, Vec<(PathBuf, u64)>> = HashMap::new();
+
+    for (path, size) in files {
+        match hash_file(&path, limit) {
+            Ok(hash) => by_hash.entry(hash).or_default().push((path, size)),
+            Err(err) => errors.push(anyhow!("error hashing '{}': {err}", path.to_string_lossy())),
+        }
+    }
+
+    by_hash
+        .into_values()
+        .filter(|files| files.len() > 1)
+        .collect()
+}
+
+/// Splits files into sets with identical contents, dropping sets of a single file.
+fn group_by_contents(
+    files: Vec<(PathBuf, u64)>,
+    errors: &mut Vec<anyhow::Error>,
+) -> Vec<Vec<(PathBuf, u64)>> {
+    let mut groups: Vec<Vec<(PathBuf, u64)>> = Vec::new();
+
+    for (path, size) in files {
+        let mut found = false;
+        for group in &mut groups {
+            match same_contents(&group[0].0, &path) {
+                Ok(true) => {
+                    group.push((path.clone(), size));
+                    found = true;
+                    break;
+                }
+                Ok(false) => {}
+                Err(err) => {
+                    errors.push(anyhow!(
+                        "error comparing '{}': {err}",
+                        path.to_string_lossy()
+                    ));
+                    found = true;
+                    break;
+                }
+            }
+        }
+        if !found {
+            groups.push(vec![(path, size)]);
+        }
+    }
+
+    groups.retain(|files| files.len() > 1);
+    groups
+}
+
+fn same_contents(a: &Path, b: &Path) -> anyhow::Result<bool> {
+    let mut a = BufReader::new(File::open(a)?);
+    let mut b = BufReader::new(File::open(b)?);
+
+    let mut buffer_a = [0u8; 64 * 1024];
+    let mut buffer_b = [0u8; 64 * 1024];
+    loop {
+        let bytes_read = read_full(&mut a, &mut buffer_a)?;
+        if bytes_read != read_full(&mut b, &mut buffer_b)? {
+            return Ok(false);
+        }
+        if bytes_read == 0 {
+            return Ok(true);
+        }
+        if buffer_a[..bytes_read] != buffer_b[..bytes_read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Reads until `buffer` is full or the end of the file, so that both sides of a
+/// comparison are read in step.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn hash_file(path: &Path, limit: Option<u64>) -> anyhow::Result<u64> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file).take(limit.unwrap_or(u64::MAX));
+
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..bytes_read]);
+    }
+
+    Ok(hasher.finish())
+}
@@ -0,0 +1,60 @@
+use std::{
+    fs::Metadata,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// Keeps a scan on the filesystem of the root directory, recording every mount
+/// point that was skipped along the way.
+pub struct DeviceBoundary {
+    #[cfg_attr(not(unix), allow(dead_code))]
+    root_dev: u64,
+    skipped: Mutex<Vec<PathBuf>>,
+}
+
+impl DeviceBoundary {
+    #[cfg(unix)]
+    pub fn new(root: &Metadata) -> Self {
+        Self {
+            root_dev: root.dev(),
+            skipped: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn new(_root: &Metadata) -> Self {
+        Self {
+            root_dev: 0,
+            skipped: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns `true` if the directory lives on the root's filesystem. Otherwise
+    /// its path is recorded as a skipped mount point.
+    pub fn try_enter(&self, path: &Path, metadata: &Metadata) -> bool {
+        if self.is_same_device(metadata) {
+            return true;
+        }
+        self.skipped.lock().unwrap().push(path.to_path_buf());
+        false
+    }
+
+    pub fn skipped(&self) -> Vec<PathBuf> {
+        let mut skipped = self.skipped.lock().unwrap().clone();
+        skipped.sort();
+        skipped
+    }
+
+    #[cfg(unix)]
+    fn is_same_device(&self, metadata: &Metadata) -> bool {
+        metadata.dev() == self.root_dev
+    }
+
+    #[cfg(not(unix))]
+    fn is_same_device(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+}
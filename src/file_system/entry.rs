@@ -1,18 +1,45 @@
-use std::{cmp::Ordering, ffi::OsString};
+use std::{cmp::Ordering, ffi::OsString, path::PathBuf, time::SystemTime};
 
-use crate::config::SortBy;
+use crate::{config::SortBy, owner::Owner};
 
 pub enum FsEntry {
+    // `size` is the measure being charted (apparent size, or allocated size in disk usage mode),
+    // while `apparent_size` and `disk_size` are always both recorded for the summary.
+    // Directories take the latest modification and access times of anything inside them,
+    // and `count` is the number of entries at any depth below them, plus the directory itself
     File {
         name: OsString,
         size: u64,
+        apparent_size: u64,
+        disk_size: u64,
         lines: Option<u64>,
+        modified: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        owner: Option<Owner>,
     },
     Dir {
         name: OsString,
         size: u64,
+        apparent_size: u64,
+        disk_size: u64,
         lines: Option<u64>,
-        // children: Option<Vec<FsEntry>>, // TODO: add option to view children heirarchy in output
+        modified: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        count: u64,
+        children: Option<Vec<FsEntry>>,
+    },
+    // Sizes are those of the link itself, or of whatever it resolves to when following links
+    Symlink {
+        name: OsString,
+        target: PathBuf,
+        size: u64,
+        apparent_size: u64,
+        disk_size: u64,
+        lines: Option<u64>,
+        modified: Option<SystemTime>,
+        accessed: Option<SystemTime>,
+        count: u64,
+        children: Option<Vec<FsEntry>>,
     },
     Unknown {
         name: OsString,
@@ -24,7 +51,10 @@ impl FsEntry {
 
     pub fn name(&self) -> &OsString {
         match self {
-            Self::File { name, .. } | Self::Dir { name, .. } | Self::Unknown { name, .. } => name,
+            Self::File { name, .. }
+            | Self::Dir { name, .. }
+            | Self::Symlink { name, .. }
+            | Self::Unknown { name, .. } => name,
         }
     }
 
@@ -35,19 +65,192 @@ impl FsEntry {
             .unwrap_or(Self::UNKNOWN_ENTRY)
     }
 
+    /// The name as shown in charts, including the target of symlinks.
+    pub fn label(&self) -> String {
+        match self {
+            Self::Symlink { target, .. } => {
+                format!("{} -> {}", self.name_str(), target.to_string_lossy())
+            }
+            _ => self.name_str().to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::File { .. } => "file",
+            Self::Dir { .. } => "dir",
+            Self::Symlink { .. } => "symlink",
+            Self::Unknown { .. } => "unknown",
+        }
+    }
+
+    pub fn target(&self) -> Option<&PathBuf> {
+        match self {
+            Self::Symlink { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
     pub fn size(&self) -> Option<u64> {
         match self {
-            Self::File { size, .. } | Self::Dir { size, .. } => Some(*size),
+            Self::File { size, .. } | Self::Dir { size, .. } | Self::Symlink { size, .. } => {
+                Some(*size)
+            }
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    pub fn apparent_size(&self) -> Option<u64> {
+        match self {
+            Self::File { apparent_size, .. }
+            | Self::Dir { apparent_size, .. }
+            | Self::Symlink { apparent_size, .. } => Some(*apparent_size),
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    pub fn disk_size(&self) -> Option<u64> {
+        match self {
+            Self::File { disk_size, .. }
+            | Self::Dir { disk_size, .. }
+            | Self::Symlink { disk_size, .. } => Some(*disk_size),
             Self::Unknown { .. } => None,
         }
     }
 
     pub fn lines(&self) -> Option<u64> {
         match self {
-            Self::File { lines, .. } | Self::Dir { lines, .. } => *lines,
+            Self::File { lines, .. } | Self::Dir { lines, .. } | Self::Symlink { lines, .. } => {
+                *lines
+            }
             _ => None,
         }
     }
+
+    /// The number of files and directories this entry takes up, itself included.
+    pub fn count(&self) -> u64 {
+        match self {
+            Self::Dir { count, .. } | Self::Symlink { count, .. } => *count,
+            Self::File { .. } | Self::Unknown { .. } => 1,
+        }
+    }
+
+    pub fn modified(&self) -> Option<SystemTime> {
+        match self {
+            Self::File { modified, .. }
+            | Self::Dir { modified, .. }
+            | Self::Symlink { modified, .. } => *modified,
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    pub fn accessed(&self) -> Option<SystemTime> {
+        match self {
+            Self::File { accessed, .. }
+            | Self::Dir { accessed, .. }
+            | Self::Symlink { accessed, .. } => *accessed,
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    pub fn owner(&self) -> Option<Owner> {
+        match self {
+            Self::File { owner, .. } => *owner,
+            _ => None,
+        }
+    }
+
+    pub fn children(&self) -> Option<&Vec<FsEntry>> {
+        match self {
+            Self::Dir { children, .. } | Self::Symlink { children, .. } => children.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Option<&mut Vec<FsEntry>> {
+        match self {
+            Self::Dir { children, .. } | Self::Symlink { children, .. } => children.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn into_children(self) -> Option<Vec<FsEntry>> {
+        match self {
+            Self::Dir { children, .. } | Self::Symlink { children, .. } => children,
+            _ => None,
+        }
+    }
+
+    pub fn drop_children(&mut self) {
+        if let Self::Dir { children, .. } | Self::Symlink { children, .. } = self {
+            *children = None;
+        }
+    }
+
+    /// Takes a removed descendant out of this entry's totals.
+    pub fn subtract(&mut self, removed: &FsEntry) {
+        match self {
+            Self::File {
+                size,
+                apparent_size,
+                disk_size,
+                lines,
+                ..
+            }
+            | Self::Dir {
+                size,
+                apparent_size,
+                disk_size,
+                lines,
+                ..
+            }
+            | Self::Symlink {
+                size,
+                apparent_size,
+                disk_size,
+                lines,
+                ..
+            } => {
+                *size = size.saturating_sub(removed.size().unwrap_or(0));
+                *apparent_size = apparent_size.saturating_sub(removed.apparent_size().unwrap_or(0));
+                *disk_size = disk_size.saturating_sub(removed.disk_size().unwrap_or(0));
+                if let (Some(lines), Some(n)) = (lines, removed.lines()) {
+                    *lines = lines.saturating_sub(n);
+                }
+            }
+            Self::Unknown { .. } => {}
+        }
+        if let Self::Dir { count, .. } | Self::Symlink { count, .. } = self {
+            *count = count.saturating_sub(removed.count());
+        }
+    }
+}
+
+/// Drops entries at any depth that `keep` rejects, taking their sizes out of the directories
+/// above them. Returns every dropped entry, so that callers can update their own totals.
+pub fn retain_entries(
+    entries: &mut Vec<FsEntry>,
+    keep: &mut impl FnMut(&FsEntry) -> bool,
+) -> Vec<FsEntry> {
+    let mut removed = Vec::new();
+
+    for fse in entries.iter_mut() {
+        if let Some(children) = fse.children_mut() {
+            let nested = retain_entries(children, keep);
+            for child in &nested {
+                fse.subtract(child);
+            }
+            removed.extend(nested);
+        }
+    }
+
+    let (kept, dropped): (Vec<FsEntry>, Vec<FsEntry>) = std::mem::take(entries)
+        .into_iter()
+        .partition(|fse| keep(fse));
+    *entries = kept;
+    removed.extend(dropped);
+
+    removed
 }
 
 pub fn sort_entries(entries: &mut [FsEntry], sort_by: &SortBy, reverse: bool) {
@@ -58,16 +261,26 @@ pub fn sort_entries(entries: &mut [FsEntry], sort_by: &SortBy, reverse: bool) {
                 .cmp(&b.name_str().to_lowercase())
         },
         SortBy::Size => |a, b| b.size().cmp(&a.size()),
+        SortBy::Modified => |a, b| b.modified().cmp(&a.modified()),
         SortBy::Type => |a, b| {
             let cmp_val = |fse: &FsEntry| match fse {
                 FsEntry::Dir { .. } => 0,
                 FsEntry::File { .. } => 1,
-                FsEntry::Unknown { .. } => 2,
+                FsEntry::Symlink { .. } => 2,
+                FsEntry::Unknown { .. } => 3,
             };
             cmp_val(a).cmp(&cmp_val(b))
         },
     };
 
+    sort_entries_by(entries, compare, reverse);
+}
+
+fn sort_entries_by(
+    entries: &mut [FsEntry],
+    compare: fn(&FsEntry, &FsEntry) -> Ordering,
+    reverse: bool,
+) {
     entries.sort_by(|a, b| {
         let mut ordering = compare(a, b);
         if reverse {
@@ -75,4 +288,10 @@ pub fn sort_entries(entries: &mut [FsEntry], sort_by: &SortBy, reverse: bool) {
         }
         ordering
     });
+
+    for fse in entries.iter_mut() {
+        if let Some(children) = fse.children_mut() {
+            sort_entries_by(children, compare, reverse);
+        }
+    }
 }
@@ -1,16 +1,14 @@
-use std::fs::DirEntry;
-
 pub enum EntryType {
     Dir,
     File,
 }
 
 impl EntryType {
-    pub fn try_match(&self, entry: &DirEntry) -> anyhow::Result<bool> {
-        let metadata = entry.metadata()?;
+    /// Symlinks are neither, as they aren't followed to tell.
+    pub fn matches(&self, is_dir: bool, is_file: bool) -> bool {
         match self {
-            Self::Dir => Ok(metadata.is_dir()),
-            Self::File => Ok(metadata.is_file()),
+            Self::Dir => is_dir,
+            Self::File => is_file,
         }
     }
 }
@@ -0,0 +1,264 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::anyhow;
+use globset::{GlobBuilder, GlobMatcher};
+
+/// Ignore files read in every directory, from lowest to highest precedence. Only the
+/// root of a repository has a `.git` directory to read excludes from.
+const IGNORE_FILES: [&str; 3] = [".git/info/exclude", ".gitignore", ".ignore"];
+
+struct IgnoreRule {
+    matcher: GlobMatcher,
+    negate: bool,
+    dir_only: bool,
+}
+
+/// The `.gitignore`-style rules in effect for a directory: its own rules layered on
+/// top of those of every parent directory, up to the root of the repository.
+pub struct IgnoreStack {
+    parent: Option<Arc<IgnoreStack>>,
+    base: PathBuf,
+    // The path from the directory holding the rules to `base`, for directories above
+    // the scanned one, whose rules are matched against paths below it
+    prefix: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreStack {
+    /// Returns the stack for the scanned directory. When it is inside a repository,
+    /// the rules of the directories between it and the repository root apply too.
+    pub fn root(dir: &Path, errors: &mut Vec<anyhow::Error>) -> Arc<Self> {
+        let mut parent = None;
+
+        if let Ok(resolved) = fs::canonicalize(dir) {
+            let ancestors: Vec<&Path> = resolved.ancestors().skip(1).collect();
+            let repo_root = match resolved.join(".git").exists() {
+                true => None,
+                false => ancestors.iter().position(|dir| dir.join(".git").exists()),
+            };
+
+            if let Some(repo_root) = repo_root {
+                // From the repository root down, so that deeper rules take precedence
+                for ancestor in ancestors[..=repo_root].iter().rev() {
+                    let rules = load_rules(ancestor, errors);
+                    if rules.is_empty() {
+                        continue;
+                    }
+                    parent = Some(Arc::new(Self {
+                        parent,
+                        base: dir.to_path_buf(),
+                        prefix: resolved
+                            .strip_prefix(ancestor)
+                            .unwrap_or(Path::new(""))
+                            .to_path_buf(),
+                        rules,
+                    }));
+                }
+            }
+        }
+
+        Arc::new(Self {
+            parent,
+            base: dir.to_path_buf(),
+            prefix: PathBuf::new(),
+            rules: load_rules(dir, errors),
+        })
+    }
+
+    /// Returns the stack for a subdirectory, which is this one if the
+    /// subdirectory has no ignore files of its own.
+    pub fn child(self: &Arc<Self>, dir: &Path, errors: &mut Vec<anyhow::Error>) -> Arc<Self> {
+        let rules = load_rules(dir, errors);
+        if rules.is_empty() {
+            return self.clone();
+        }
+        Arc::new(Self {
+            parent: Some(self.clone()),
+            base: dir.to_path_buf(),
+            prefix: PathBuf::new(),
+            rules,
+        })
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        // The .git directory itself is never part of what gets committed
+        if is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+
+        let mut stack = Some(self);
+        while let Some(layer) = stack {
+            if let Ok(rel) = path.strip_prefix(&layer.base) {
+                let rel = layer.prefix.join(rel);
+                // Later rules and deeper directories take precedence
+                for rule in layer.rules.iter().rev() {
+                    if rule.dir_only && !is_dir {
+                        continue;
+                    }
+                    if rule.matcher.is_match(&rel) {
+                        return !rule.negate;
+                    }
+                }
+            }
+            stack = layer.parent.as_deref();
+        }
+
+        false
+    }
+}
+
+fn load_rules(dir: &Path, errors: &mut Vec<anyhow::Error>) -> Vec<IgnoreRule> {
+    let mut rules = Vec::new();
+
+    for file_name in IGNORE_FILES {
+        let path = dir.join(file_name);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => {
+                errors.push(anyhow!(
+                    "error reading ignore file '{}': {err}",
+                    path.to_string_lossy()
+                ));
+                continue;
+            }
+        };
+
+        for line in contents.lines() {
+            match parse_rule(line) {
+                Ok(Some(rule)) => rules.push(rule),
+                Ok(None) => {}
+                Err(err) => errors.push(anyhow!(
+                    "invalid pattern '{}' in '{}': {err}",
+                    line,
+                    path.to_string_lossy()
+                )),
+            }
+        }
+    }
+
+    rules
+}
+
+fn parse_rule(line: &str) -> Result<Option<IgnoreRule>, globset::Error> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (negate, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+
+    // Patterns containing a slash are relative to the ignore file's directory,
+    // all others match at any depth below it
+    let glob = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{pattern}"),
+    };
+    if glob.is_empty() {
+        return Ok(None);
+    }
+
+    let matcher = GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+
+    Ok(Some(IgnoreRule {
+        matcher,
+        negate,
+        dir_only,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(line: &str) -> IgnoreRule {
+        parse_rule(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn skips_blank_lines_and_comments() {
+        for line in ["", "   ", "# comment"] {
+            assert!(
+                parse_rule(line).unwrap().is_none(),
+                "{line:?} should be skipped"
+            );
+        }
+    }
+
+    #[test]
+    fn unanchored_patterns_match_at_any_depth() {
+        let rule = rule("*.log");
+        assert!(rule.matcher.is_match("debug.log"));
+        assert!(rule.matcher.is_match("a/b/debug.log"));
+        assert!(!rule.negate && !rule.dir_only);
+    }
+
+    #[test]
+    fn anchored_patterns_match_from_the_base() {
+        let rule = rule("/build");
+        assert!(rule.matcher.is_match("build"));
+        assert!(!rule.matcher.is_match("src/build"));
+
+        // A slash in the middle anchors a pattern too
+        let rule = self::rule("docs/*.md");
+        assert!(rule.matcher.is_match("docs/a.md"));
+        assert!(!rule.matcher.is_match("src/docs/a.md"));
+        assert!(!rule.matcher.is_match("docs/sub/a.md"));
+    }
+
+    #[test]
+    fn negated_patterns() {
+        let rule = rule("!keep.log");
+        assert!(rule.negate);
+        assert!(rule.matcher.is_match("a/keep.log"));
+
+        // An escaped '!' is part of the name
+        let rule = self::rule("\\!important");
+        assert!(!rule.negate);
+        assert!(rule.matcher.is_match("!important"));
+    }
+
+    #[test]
+    fn directory_only_patterns() {
+        let rule = rule("target/");
+        assert!(rule.dir_only);
+        assert!(rule.matcher.is_match("target"));
+        assert!(rule.matcher.is_match("crates/x/target"));
+    }
+
+    #[test]
+    fn applies_rules_from_the_repository_root() {
+        let repo = std::env::temp_dir().join(format!("ds-ignore-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(repo.join(".git/info")).unwrap();
+        fs::create_dir_all(repo.join("src/target")).unwrap();
+        fs::write(repo.join(".gitignore"), "target/\n/src/generated.rs\n").unwrap();
+        fs::write(repo.join(".git/info/exclude"), "*.local\n").unwrap();
+
+        let src = repo.join("src");
+        let stack = IgnoreStack::root(&src, &mut Vec::new());
+        assert!(stack.is_ignored(&src.join("target"), true));
+        assert!(stack.is_ignored(&src.join("generated.rs"), false));
+        assert!(stack.is_ignored(&src.join("notes.local"), false));
+        assert!(!stack.is_ignored(&src.join("main.rs"), false));
+
+        fs::remove_dir_all(&repo).unwrap();
+    }
+}
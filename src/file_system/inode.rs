@@ -0,0 +1,74 @@
+use std::{
+    collections::HashSet,
+    fs::Metadata,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+/// A set of `(dev, ino)` pairs shared between reader threads, used to count files
+/// with multiple hard links (or directories reachable through symlinks) only once.
+#[derive(Default)]
+pub struct InodeSet {
+    seen: Mutex<HashSet<(u64, u64)>>,
+    duplicates: AtomicUsize,
+    // Set when following symlinks, which can reach a file with a single link twice
+    every_file: bool,
+}
+
+impl InodeSet {
+    /// A set that records every file, not just those with multiple hard links.
+    pub fn every_file() -> Self {
+        Self {
+            every_file: true,
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` the first time an inode is seen, and `false` for every
+    /// further hard link (or followed symlink) pointing to it.
+    #[cfg(unix)]
+    pub fn insert(&self, metadata: &Metadata) -> bool {
+        if !self.every_file && metadata.nlink() <= 1 {
+            return true;
+        }
+
+        let is_new = self
+            .seen
+            .lock()
+            .unwrap()
+            .insert((metadata.dev(), metadata.ino()));
+        // Only extra hard links are reported, not files reached again through a symlink
+        if !is_new && metadata.nlink() > 1 {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+        }
+        is_new
+    }
+
+    #[cfg(not(unix))]
+    pub fn insert(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    /// Returns `true` the first time an inode is seen, regardless of its link count.
+    #[cfg(unix)]
+    pub fn visit(&self, metadata: &Metadata) -> bool {
+        self.seen
+            .lock()
+            .unwrap()
+            .insert((metadata.dev(), metadata.ino()))
+    }
+
+    #[cfg(not(unix))]
+    pub fn visit(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    pub fn duplicates(&self) -> usize {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,362 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, ErrorKind},
+    path::Path,
+    sync::Mutex,
+};
+
+pub struct Language {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comments: &'static [(&'static str, &'static str)],
+}
+
+const C_LINE: &[&str] = &["//"];
+const C_BLOCK: &[(&str, &str)] = &[("/*", "*/")];
+const HASH_LINE: &[&str] = &["#"];
+const NONE_LINE: &[&str] = &[];
+const NONE_BLOCK: &[(&str, &str)] = &[];
+const XML_BLOCK: &[(&str, &str)] = &[("<!--", "-->")];
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        name: "Rust",
+        extensions: &["rs"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "C",
+        extensions: &["c", "h"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "C++",
+        extensions: &["cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "C#",
+        extensions: &["cs"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Go",
+        extensions: &["go"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Java",
+        extensions: &["java"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Kotlin",
+        extensions: &["kt", "kts"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Swift",
+        extensions: &["swift"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "JavaScript",
+        extensions: &["js", "mjs", "cjs", "jsx"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "TypeScript",
+        extensions: &["ts", "mts", "cts", "tsx"],
+        line_comments: C_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "CSS",
+        extensions: &["css", "scss", "less"],
+        line_comments: NONE_LINE,
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Python",
+        extensions: &["py", "pyi"],
+        line_comments: HASH_LINE,
+        block_comments: &[("\"\"\"", "\"\"\""), ("'''", "'''")],
+    },
+    Language {
+        name: "Ruby",
+        extensions: &["rb"],
+        line_comments: HASH_LINE,
+        block_comments: &[("=begin", "=end")],
+    },
+    Language {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh", "fish"],
+        line_comments: HASH_LINE,
+        block_comments: NONE_BLOCK,
+    },
+    Language {
+        name: "TOML",
+        extensions: &["toml"],
+        line_comments: HASH_LINE,
+        block_comments: NONE_BLOCK,
+    },
+    Language {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        line_comments: HASH_LINE,
+        block_comments: NONE_BLOCK,
+    },
+    Language {
+        name: "SQL",
+        extensions: &["sql"],
+        line_comments: &["--"],
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "Lua",
+        extensions: &["lua"],
+        line_comments: &["--"],
+        block_comments: &[("--[[", "]]")],
+    },
+    Language {
+        name: "Haskell",
+        extensions: &["hs"],
+        line_comments: &["--"],
+        block_comments: &[("{-", "-}")],
+    },
+    Language {
+        name: "PHP",
+        extensions: &["php"],
+        line_comments: &["//", "#"],
+        block_comments: C_BLOCK,
+    },
+    Language {
+        name: "HTML",
+        extensions: &["html", "htm"],
+        line_comments: NONE_LINE,
+        block_comments: XML_BLOCK,
+    },
+    Language {
+        name: "XML",
+        extensions: &["xml", "svg"],
+        line_comments: NONE_LINE,
+        block_comments: XML_BLOCK,
+    },
+    Language {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        line_comments: NONE_LINE,
+        block_comments: XML_BLOCK,
+    },
+    Language {
+        name: "JSON",
+        extensions: &["json"],
+        line_comments: NONE_LINE,
+        block_comments: NONE_BLOCK,
+    },
+];
+
+impl Language {
+    pub fn from_path(path: &Path) -> Option<&'static Language> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        LANGUAGES
+            .iter()
+            .find(|lang| lang.extensions.contains(&ext.as_str()))
+    }
+
+    /// Splits a file into code, comment and blank lines. Non UTF-8 files are
+    /// treated as binary and count as zero lines.
+    pub fn count_lines(&self, path: &Path) -> anyhow::Result<LineStats> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut stats = LineStats {
+            files: 1,
+            ..Default::default()
+        };
+
+        // The end marker of the block comment we are currently inside of
+        let mut block_end: Option<&str> = None;
+
+        // Lines are streamed, so that large dumps aren't read into memory all at once
+        for line in reader.lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) if err.kind() == ErrorKind::InvalidData => {
+                    return Ok(LineStats {
+                        files: 1,
+                        ..Default::default()
+                    })
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let line = line.trim();
+
+            if let Some(end) = block_end {
+                stats.comment += 1;
+                if line.contains(end) {
+                    block_end = None;
+                }
+                continue;
+            }
+
+            if line.is_empty() {
+                stats.blank += 1;
+                continue;
+            }
+
+            // Block comments go first, as some start with a line comment marker, like
+            // Lua's `--[[`
+            if let Some((start, end)) = self
+                .block_comments
+                .iter()
+                .find(|(start, _)| line.starts_with(start))
+            {
+                stats.comment += 1;
+                if !line[start.len()..].contains(end) {
+                    block_end = Some(end);
+                }
+                continue;
+            }
+
+            if self.line_comments.iter().any(|c| line.starts_with(c)) {
+                stats.comment += 1;
+                continue;
+            }
+
+            stats.code += 1;
+            // A block comment opened after code on the same line
+            for (start, end) in self.block_comments {
+                if let Some(i) = line.find(start) {
+                    if !line[i + start.len()..].contains(end) {
+                        block_end = Some(end);
+                    }
+                    break;
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct LineStats {
+    pub files: u64,
+    pub code: u64,
+    pub comment: u64,
+    pub blank: u64,
+}
+
+impl LineStats {
+    pub fn lines(&self) -> u64 {
+        self.code + self.comment + self.blank
+    }
+
+    fn add(&mut self, other: &LineStats) {
+        self.files += other.files;
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// Per-language line counts, added to by every reader thread.
+#[derive(Default)]
+pub struct LanguageStats {
+    by_language: Mutex<HashMap<&'static str, LineStats>>,
+}
+
+impl LanguageStats {
+    pub fn add(&self, language: &Language, stats: &LineStats) {
+        self.by_language
+            .lock()
+            .unwrap()
+            .entry(language.name)
+            .or_default()
+            .add(stats);
+    }
+
+    /// Returns the counts of every language seen, with the most code first.
+    pub fn sorted(&self) -> Vec<(&'static str, LineStats)> {
+        let mut sorted: Vec<_> = self
+            .by_language
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, stats)| (*name, *stats))
+            .collect();
+        sorted.sort_by(|a, b| b.1.code.cmp(&a.1.code).then(a.0.cmp(b.0)));
+        sorted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn count(file_name: &str, contents: impl AsRef<[u8]>) -> LineStats {
+        let path = env::temp_dir().join(format!("ds-lines-{}-{file_name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        let language = Language::from_path(&path).unwrap();
+        let stats = language.count_lines(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        stats
+    }
+
+    fn assert_counts(stats: LineStats, code: u64, comment: u64, blank: u64) {
+        assert_eq!(
+            (stats.code, stats.comment, stats.blank),
+            (code, comment, blank)
+        );
+    }
+
+    #[test]
+    fn counts_code_comments_and_blanks() {
+        let stats = count(
+            "a.rs",
+            "// line\nfn main() {\n\n    /* block\n    still */\n}\n",
+        );
+        assert_eq!(stats.files, 1);
+        assert_counts(stats, 2, 3, 1);
+    }
+
+    #[test]
+    fn counts_block_comments_opened_after_code() {
+        let stats = count("a.c", "int x; /* starts\nends */\nint y; /* closed */\n");
+        assert_counts(stats, 2, 1, 0);
+    }
+
+    #[test]
+    fn block_comments_starting_with_a_line_comment_marker() {
+        let stats = count("a.lua", "--[[\nblock\nmore\n]]\nprint(1)\n");
+        assert_counts(stats, 1, 4, 0);
+
+        let stats = count("b.lua", "-- line\n--[[ closed ]]\nprint(1)\n");
+        assert_counts(stats, 1, 2, 0);
+    }
+
+    #[test]
+    fn python_docstrings() {
+        let stats = count("a.py", "\"\"\"\nDocs\n\"\"\"\n# comment\nx = 1\n");
+        assert_counts(stats, 1, 4, 0);
+    }
+
+    #[test]
+    fn non_utf8_files_count_as_zero_lines() {
+        let stats = count("a.rs", b"fn main() {}\n\xff\xfe\n");
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.lines(), 0);
+    }
+}
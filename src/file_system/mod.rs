@@ -1,3 +1,7 @@
+pub mod device;
 pub mod entry;
 pub mod entry_type;
+pub mod ignore;
+pub mod inode;
+pub mod language;
 pub mod read;
@@ -1,64 +1,412 @@
 use std::{
-    fs::{self, DirEntry, File},
+    ffi::OsString,
+    fs::{self, DirEntry, File, Metadata},
     io::{BufReader, Read},
+    path::{Path, PathBuf},
     sync::{
-        mpsc::{self, Receiver},
-        Arc,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
     },
     thread::{self, JoinHandle},
+    time::SystemTime,
 };
 
 use anyhow::anyhow;
 
-use crate::{file_system::entry::FsEntry, ok_or, utils::sync::Semaphore};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::{
+    aggregate::Aggregates,
+    file_system::{
+        device::DeviceBoundary,
+        entry::FsEntry,
+        ignore::IgnoreStack,
+        inode::InodeSet,
+        language::{Language, LanguageStats},
+    },
+    filter::{DirEntryFilter, SizeFilter},
+    ok_or,
+    owner::Owner,
+    utils::sync::WorkPool,
+};
+
+pub type ReaderResult = (FsEntry, Option<Aggregates>, Vec<anyhow::Error>);
+
+#[derive(Clone, Default)]
+pub struct ReadOptions {
+    pub count_lines: bool,
+    pub disk_usage: bool,
+    pub keep_children: bool,
+    pub hard_links: Option<Arc<InodeSet>>,
+    pub device_boundary: Option<Arc<DeviceBoundary>>,
+    // Set when following symlinks, so that every directory is only entered once
+    pub visited_dirs: Option<Arc<InodeSet>>,
+    // Applied to nested entries; top-level entries are filtered before the readers are spawned
+    pub filter: Option<Arc<DirEntryFilter>>,
+    // Ignore rules of the scanned directory, which nested directories add their own rules to
+    pub ignore: Option<Arc<IgnoreStack>>,
+    // Nested files outside of these bounds are left out of their directory's total
+    pub file_filter: Option<SizeFilter>,
+    // Set to break down line counts by language, which requires `count_lines`
+    pub languages: Option<Arc<LanguageStats>>,
+    // Empty breakdowns, a copy of which totals up the files of each top-level entry
+    pub aggregates: Option<Arc<Aggregates>>,
+}
+
+impl ReadOptions {
+    fn follow_links(&self) -> bool {
+        self.visited_dirs.is_some()
+    }
+
+    fn new_aggregates(&self) -> Option<Aggregates> {
+        self.aggregates.as_deref().map(Aggregates::empty)
+    }
+
+    fn measured_size(&self, apparent_size: u64, disk_size: u64) -> u64 {
+        if self.disk_usage {
+            disk_size
+        } else {
+            apparent_size
+        }
+    }
+}
 
 pub fn spawn_readers(
     entries: Vec<DirEntry>, // TODO: refactor to be a &[DirEntry] ?
     max_threads: Option<usize>,
-    count_lines: bool,
-) -> (Receiver<(FsEntry, Vec<anyhow::Error>)>, Vec<JoinHandle<()>>) {
+    opts: ReadOptions,
+) -> (Receiver<ReaderResult>, Vec<JoinHandle<()>>) {
     let (tx, rx) = mpsc::channel();
-    let mut handles = Vec::new();
 
-    let sem = match max_threads {
-        Some(n) => Some(Arc::new(Semaphore::new(n))),
-        None => None,
-    };
+    let threads = max_threads.unwrap_or_else(|| {
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    });
+
+    let jobs = entries
+        .into_iter()
+        .map(|entry| Job::Read {
+            entry,
+            ignore: opts.ignore.clone(),
+            parent: Parent::Root(tx.clone()),
+        })
+        .collect();
+
+    let pool = WorkPool::new(jobs);
+    let handles = pool.spawn(threads, move |job, pool| run_job(job, &opts, pool));
+
+    (rx, handles)
+}
+
+/// A unit of work for the reader pool. Directories are split into one job that
+/// lists their entries, plus one job per subdirectory, so that large subtrees are
+/// spread across all workers at every depth.
+enum Job {
+    Read {
+        entry: DirEntry,
+        ignore: Option<Arc<IgnoreStack>>,
+        parent: Parent,
+    },
+    Expand {
+        dir: PendingDir,
+        parent: Parent,
+    },
+}
+
+/// Where a finished entry is delivered: the result channel for top-level entries,
+/// or a slot in the directory that contains it.
+enum Parent {
+    Root(Sender<ReaderResult>),
+    Dir { node: Arc<DirNode>, slot: usize },
+}
 
-    for entry in entries {
-        let sem = sem.clone();
-        let tx = tx.clone();
+/// A directory that still has to be listed.
+struct PendingDir {
+    name: OsString,
+    path: PathBuf,
+    // Set when the directory was reached by following a symlink
+    target: Option<PathBuf>,
+    apparent_size: u64,
+    disk_size: u64,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    ignore: Option<Arc<IgnoreStack>>,
+}
+
+/// A directory whose entries are being read, possibly by several workers at once.
+struct DirNode {
+    // Subdirectory jobs still running, plus one until listing the directory is done
+    remaining: AtomicUsize,
+    state: Mutex<DirState>,
+}
 
-        let handle = thread::spawn(move || {
-            if let Some(sem) = &sem {
-                sem.lock();
+struct DirState {
+    name: OsString,
+    target: Option<PathBuf>,
+    apparent_size: u64,
+    disk_size: u64,
+    lines: Option<u64>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    count: u64,
+    children: Option<Vec<Option<FsEntry>>>,
+    aggregates: Option<Aggregates>,
+    errors: Vec<anyhow::Error>,
+    parent: Option<Parent>,
+}
+
+enum ReadResult {
+    Done(FsEntry),
+    Dir(PendingDir),
+}
+
+fn run_job(job: Job, opts: &ReadOptions, pool: &WorkPool<Job>) {
+    match job {
+        Job::Read {
+            entry,
+            ignore,
+            parent,
+        } => {
+            let mut errors = Vec::new();
+            match read_entry(&entry, opts, ignore.as_ref(), &mut errors) {
+                ReadResult::Done(fse) => {
+                    let mut aggregates = opts.new_aggregates();
+                    if let Some(aggregates) = &mut aggregates {
+                        aggregates.add_file(&fse, &entry.path());
+                    }
+                    deliver(parent, fse, aggregates, errors, opts)
+                }
+                ReadResult::Dir(dir) => expand_dir(dir, parent, errors, opts, pool),
             }
+        }
+        Job::Expand { dir, parent } => expand_dir(dir, parent, Vec::new(), opts, pool),
+    }
+}
 
-            let mut errs = Vec::new();
+fn deliver(
+    parent: Parent,
+    fse: FsEntry,
+    aggregates: Option<Aggregates>,
+    errors: Vec<anyhow::Error>,
+    opts: &ReadOptions,
+) {
+    match parent {
+        Parent::Root(tx) => {
+            let path = fse.name().to_string_lossy().into_owned();
+            tx.send((fse, aggregates, errors))
+                .unwrap_or_else(|_| panic!("Reader for '{}' failed to send", path));
+        }
+        Parent::Dir { node, slot } => {
+            {
+                let mut state = node.state.lock().unwrap();
+                state.add_child(slot, fse);
+                if let (Some(totals), Some(aggregates)) = (&mut state.aggregates, aggregates) {
+                    totals.merge(aggregates);
+                }
+                state.errors.extend(errors);
+            }
+            node.finish_one(opts);
+        }
+    }
+}
 
-            let fse = read_entry_recursive(&entry, count_lines, &mut errs);
+fn expand_dir(
+    dir: PendingDir,
+    parent: Parent,
+    mut errors: Vec<anyhow::Error>,
+    opts: &ReadOptions,
+    pool: &WorkPool<Job>,
+) {
+    let ignore = dir
+        .ignore
+        .as_ref()
+        .map(|stack| stack.child(&dir.path, &mut errors));
 
-            tx.send((fse, errs)).expect(&format!(
-                "Reader thread '{}' failed to send",
-                entry.path().to_string_lossy()
-            ));
+    let node = Arc::new(DirNode {
+        remaining: AtomicUsize::new(1),
+        state: Mutex::new(DirState {
+            name: dir.name,
+            target: dir.target,
+            apparent_size: dir.apparent_size,
+            disk_size: dir.disk_size,
+            lines: opts.count_lines.then_some(0),
+            modified: dir.modified,
+            accessed: dir.accessed,
+            count: 1,
+            children: opts.keep_children.then(Vec::new),
+            aggregates: opts.new_aggregates(),
+            errors: Vec::new(),
+            parent: Some(parent),
+        }),
+    });
 
-            if let Some(sem) = &sem {
-                sem.unlock();
+    match fs::read_dir(&dir.path) {
+        Ok(it) => {
+            for result in it {
+                let en = ok_or!(result, err => {
+                    errors.push(anyhow!(
+                        "error reading dir entry '{}': {err}",
+                        dir.path.to_string_lossy(),
+                    ));
+                    continue;
+                });
+
+                if let Some(ignore) = &ignore {
+                    let is_dir = en.file_type().is_ok_and(|ft| ft.is_dir());
+                    if ignore.is_ignored(&en.path(), is_dir) {
+                        continue;
+                    }
+                }
+
+                if let Some(filter) = &opts.filter {
+                    match filter.try_match_recursive(&en) {
+                        Ok(true) => { /* continue on */ }
+                        Ok(false) => continue,
+                        Err(err) => {
+                            errors.push(err);
+                            continue;
+                        }
+                    }
+                }
+
+                // Files are read right away, while subdirectories become jobs of their own
+                match read_entry(&en, opts, ignore.as_ref(), &mut errors) {
+                    ReadResult::Done(fse) => {
+                        if let (FsEntry::File { .. }, Some(file_filter)) = (&fse, &opts.file_filter)
+                        {
+                            if !file_filter.matches(&fse) {
+                                continue;
+                            }
+                        }
+                        let mut state = node.state.lock().unwrap();
+                        if let Some(aggregates) = &mut state.aggregates {
+                            aggregates.add_file(&fse, &en.path());
+                        }
+                        let slot = state.reserve_slot();
+                        state.add_child(slot, fse);
+                    }
+                    ReadResult::Dir(sub_dir) => {
+                        let slot = node.state.lock().unwrap().reserve_slot();
+                        node.remaining.fetch_add(1, Ordering::SeqCst);
+                        pool.push(Job::Expand {
+                            dir: sub_dir,
+                            parent: Parent::Dir {
+                                node: node.clone(),
+                                slot,
+                            },
+                        });
+                    }
+                }
             }
-        });
+        }
+        Err(err) => {
+            errors.push(anyhow!(
+                "error reading dir '{}': {err}",
+                dir.path.to_string_lossy()
+            ));
+        }
+    };
+
+    node.state.lock().unwrap().errors.extend(errors);
+    node.finish_one(opts);
+}
+
+impl DirNode {
+    /// Called once per finished subdirectory and once after listing. The last call
+    /// completes the directory and hands it to its own parent.
+    fn finish_one(&self, opts: &ReadOptions) {
+        if self.remaining.fetch_sub(1, Ordering::SeqCst) != 1 {
+            return;
+        }
+
+        let (fse, aggregates, errors, parent) = {
+            let mut state = self.state.lock().unwrap();
+            let children = state
+                .children
+                .take()
+                .map(|children| children.into_iter().flatten().collect());
+            let size = opts.measured_size(state.apparent_size, state.disk_size);
+
+            let fse = match state.target.take() {
+                Some(target) => FsEntry::Symlink {
+                    name: std::mem::take(&mut state.name),
+                    target,
+                    size,
+                    apparent_size: state.apparent_size,
+                    disk_size: state.disk_size,
+                    lines: state.lines,
+                    modified: state.modified,
+                    accessed: state.accessed,
+                    count: state.count,
+                    children,
+                },
+                None => FsEntry::Dir {
+                    name: std::mem::take(&mut state.name),
+                    size,
+                    apparent_size: state.apparent_size,
+                    disk_size: state.disk_size,
+                    lines: state.lines,
+                    modified: state.modified,
+                    accessed: state.accessed,
+                    count: state.count,
+                    children,
+                },
+            };
+
+            let parent = state
+                .parent
+                .take()
+                .expect("directory should only be completed once");
+            let aggregates = state.aggregates.take();
+            (fse, aggregates, std::mem::take(&mut state.errors), parent)
+        };
 
-        handles.push(handle);
+        deliver(parent, fse, aggregates, errors, opts);
     }
+}
 
-    (rx, handles)
+impl DirState {
+    fn reserve_slot(&mut self) -> usize {
+        match &mut self.children {
+            Some(children) => {
+                children.push(None);
+                children.len() - 1
+            }
+            None => 0,
+        }
+    }
+
+    fn add_child(&mut self, slot: usize, fse: FsEntry) {
+        if let Some(n) = fse.apparent_size() {
+            self.apparent_size += n;
+        }
+        if let Some(n) = fse.disk_size() {
+            self.disk_size += n;
+        }
+        self.count += fse.count();
+        if let Some(n) = fse.lines() {
+            self.lines = match self.lines {
+                Some(lns) => Some(lns + n),
+                None => Some(n),
+            };
+        }
+        self.modified = self.modified.max(fse.modified());
+        self.accessed = self.accessed.max(fse.accessed());
+        if let Some(children) = &mut self.children {
+            children[slot] = Some(fse);
+        }
+    }
 }
 
-fn read_entry_recursive(
+fn read_entry(
     entry: &DirEntry,
-    count_lines: bool,
+    opts: &ReadOptions,
+    ignore: Option<&Arc<IgnoreStack>>,
     errors: &mut Vec<anyhow::Error>,
-) -> FsEntry {
+) -> ReadResult {
     let name = entry.file_name();
 
     let metadata = ok_or!(entry.metadata(), err => {
@@ -66,12 +414,45 @@ fn read_entry_recursive(
             "error getting metadata for '{}': {err}",
             name.to_string_lossy(),
         ));
-        return FsEntry::Unknown { name };
+        return ReadResult::Done(FsEntry::Unknown { name });
     });
 
+    if metadata.is_symlink() {
+        return read_symlink(name, &entry.path(), &metadata, opts, ignore, errors);
+    }
+
+    read_path(name, &entry.path(), &metadata, opts, ignore, errors)
+}
+
+fn read_path(
+    name: OsString,
+    path: &Path,
+    metadata: &Metadata,
+    opts: &ReadOptions,
+    ignore: Option<&Arc<IgnoreStack>>,
+    errors: &mut Vec<anyhow::Error>,
+) -> ReadResult {
     if metadata.is_file() {
-        let lines = match count_lines {
-            true => match read_and_count_lines(entry) {
+        let is_first_link = match &opts.hard_links {
+            Some(hard_links) => hard_links.insert(metadata),
+            None => true,
+        };
+        if !is_first_link {
+            // Additional hard links to an already counted inode take up no extra space
+            return ReadResult::Done(FsEntry::File {
+                name,
+                size: 0,
+                apparent_size: 0,
+                disk_size: 0,
+                lines: opts.count_lines.then_some(0),
+                modified: metadata.modified().ok(),
+                accessed: metadata.accessed().ok(),
+                owner: Owner::of(metadata),
+            });
+        }
+
+        let lines = match opts.count_lines {
+            true => match count_file_lines(path, opts) {
                 Ok(lines) => Some(lines),
                 Err(err) => {
                     errors.push(err);
@@ -81,63 +462,165 @@ fn read_entry_recursive(
             false => None,
         };
 
-        return FsEntry::File {
+        let apparent_size = metadata.len();
+        let disk_size = allocated_size(metadata);
+
+        return ReadResult::Done(FsEntry::File {
             name,
-            size: metadata.len(),
+            size: opts.measured_size(apparent_size, disk_size),
+            apparent_size,
+            disk_size,
             lines,
-        };
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            owner: Owner::of(metadata),
+        });
     }
 
     if metadata.is_dir() {
-        let path = entry.path();
-
-        let mut size = metadata.len();
-        let mut lines = match count_lines {
-            true => Some(0),
-            false => None,
+        let is_first_visit = match &opts.visited_dirs {
+            Some(visited_dirs) => visited_dirs.visit(metadata),
+            None => true,
+        };
+        let is_same_device = match &opts.device_boundary {
+            Some(boundary) => boundary.try_enter(path, metadata),
+            None => true,
         };
+        if !is_first_visit || !is_same_device {
+            return ReadResult::Done(FsEntry::Dir {
+                name,
+                size: 0,
+                apparent_size: 0,
+                disk_size: 0,
+                lines: opts.count_lines.then_some(0),
+                modified: metadata.modified().ok(),
+                accessed: metadata.accessed().ok(),
+                count: 1,
+                children: opts.keep_children.then(Vec::new),
+            });
+        }
 
-        match fs::read_dir(&path) {
-            Ok(it) => {
-                for result in it {
-                    let en = ok_or!(result , err => {
-                        errors.push(anyhow!(
-                            "error reading dir entry '{}': {err}",
-                            entry.file_name().to_string_lossy(),
-                        ));
-                        continue;
-                    });
+        return ReadResult::Dir(PendingDir {
+            name,
+            path: path.to_path_buf(),
+            target: None,
+            apparent_size: metadata.len(),
+            disk_size: allocated_size(metadata),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            ignore: ignore.cloned(),
+        });
+    }
 
-                    // TODO: should this be done in a new thread?
-                    let fse = read_entry_recursive(&en, count_lines, errors);
+    ReadResult::Done(FsEntry::Unknown { name })
+}
 
-                    if let Some(n) = fse.size() {
-                        size += n;
-                    }
-                    if let Some(n) = fse.lines() {
-                        lines = match lines {
-                            Some(lns) => Some(lns + n),
-                            None => Some(n),
-                        };
-                    }
-                }
-            }
+fn read_symlink(
+    name: OsString,
+    path: &Path,
+    metadata: &Metadata,
+    opts: &ReadOptions,
+    ignore: Option<&Arc<IgnoreStack>>,
+    errors: &mut Vec<anyhow::Error>,
+) -> ReadResult {
+    let target = ok_or!(fs::read_link(path), err => {
+        errors.push(anyhow!(
+            "error reading symlink '{}': {err}",
+            path.to_string_lossy(),
+        ));
+        return ReadResult::Done(FsEntry::Unknown { name });
+    });
+
+    let resolved = if opts.follow_links() {
+        match fs::metadata(path) {
+            Ok(target_metadata) => Some(read_path(
+                name.clone(),
+                path,
+                &target_metadata,
+                opts,
+                ignore,
+                errors,
+            )),
             Err(err) => {
                 errors.push(anyhow!(
-                    "error reading dir '{}': {err}",
-                    path.to_string_lossy()
+                    "error resolving symlink '{}': {err}",
+                    path.to_string_lossy(),
                 ));
+                None
             }
-        };
+        }
+    } else {
+        None
+    };
 
-        return FsEntry::Dir { name, size, lines };
+    match resolved {
+        Some(ReadResult::Dir(dir)) => ReadResult::Dir(PendingDir {
+            target: Some(target),
+            ..dir
+        }),
+        Some(ReadResult::Done(fse)) => {
+            let (size, apparent_size, disk_size) = (
+                fse.size().unwrap_or(0),
+                fse.apparent_size().unwrap_or(0),
+                fse.disk_size().unwrap_or(0),
+            );
+            ReadResult::Done(FsEntry::Symlink {
+                name,
+                target,
+                size,
+                apparent_size,
+                disk_size,
+                lines: fse.lines(),
+                modified: fse.modified(),
+                accessed: fse.accessed(),
+                count: fse.count(),
+                children: fse.into_children(),
+            })
+        }
+        None => {
+            // An unfollowed symlink only takes up the space of the link itself
+            let apparent_size = metadata.len();
+            let disk_size = allocated_size(metadata);
+            ReadResult::Done(FsEntry::Symlink {
+                name,
+                target,
+                size: opts.measured_size(apparent_size, disk_size),
+                apparent_size,
+                disk_size,
+                lines: None,
+                modified: metadata.modified().ok(),
+                accessed: metadata.accessed().ok(),
+                count: 1,
+                children: None,
+            })
+        }
     }
+}
 
-    FsEntry::Unknown { name }
+#[cfg(unix)]
+pub fn allocated_size(metadata: &Metadata) -> u64 {
+    // st_blocks is always counted in 512-byte units, regardless of the filesystem block size
+    metadata.blocks() * 512
 }
 
-fn read_and_count_lines(entry: &DirEntry) -> anyhow::Result<u64> {
-    let file = File::open(entry.path())?;
+#[cfg(not(unix))]
+pub fn allocated_size(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+fn count_file_lines(path: &Path, opts: &ReadOptions) -> anyhow::Result<u64> {
+    if let Some(languages) = &opts.languages {
+        if let Some(language) = Language::from_path(path) {
+            let stats = language.count_lines(path)?;
+            languages.add(language, &stats);
+            return Ok(stats.lines());
+        }
+    }
+    read_and_count_lines(path)
+}
+
+fn read_and_count_lines(path: &Path) -> anyhow::Result<u64> {
+    let file = File::open(path)?;
     let mut reader = BufReader::new(file);
 
     // A low byte chunk size should be used here because we want to quickly disqualify files
@@ -148,6 +631,8 @@ fn read_and_count_lines(entry: &DirEntry) -> anyhow::Result<u64> {
     let mut buffer = [0u8; CHUNK_SIZE];
     let mut leftover = Vec::new(); // TODO: use OnceCell for leftover?
     let mut lines = 0;
+    // Lines are counted by their terminating '\n', since a line can span several chunks
+    let mut ends_with_newline = true;
 
     loop {
         let bytes_read = reader.read(&mut buffer)?;
@@ -161,7 +646,8 @@ fn read_and_count_lines(entry: &DirEntry) -> anyhow::Result<u64> {
 
         match str::from_utf8(&chunk) {
             Ok(s) => {
-                lines += s.lines().count() as u64;
+                lines += s.matches('\n').count() as u64;
+                ends_with_newline = s.ends_with('\n');
                 leftover.clear();
             }
             Err(err) => {
@@ -171,8 +657,9 @@ fn read_and_count_lines(entry: &DirEntry) -> anyhow::Result<u64> {
                     // No valid UTF-8, likely a binary file
                     return Ok(0);
                 }
-                let valid = &chunk[..valid_up_to];
-                lines += str::from_utf8(valid).unwrap().lines().count() as u64;
+                let valid = str::from_utf8(&chunk[..valid_up_to]).unwrap();
+                lines += valid.matches('\n').count() as u64;
+                ends_with_newline = valid.ends_with('\n');
                 leftover = chunk[valid_up_to..].to_vec();
             }
         }
@@ -181,12 +668,18 @@ fn read_and_count_lines(entry: &DirEntry) -> anyhow::Result<u64> {
     // If leftover bytes remain, check if they form a valid UTF-8 character
     if !leftover.is_empty() {
         if let Ok(s) = str::from_utf8(&leftover) {
-            lines += s.lines().count() as u64;
+            lines += s.matches('\n').count() as u64;
+            ends_with_newline = s.ends_with('\n');
         } else {
             // invalid UTF-8, likely a binary file
             return Ok(0);
         }
     }
 
+    // A last line without a trailing newline still counts
+    if !ends_with_newline {
+        lines += 1;
+    }
+
     Ok(lines)
 }
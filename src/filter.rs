@@ -1,8 +1,9 @@
-use std::fs::DirEntry;
+use std::{ffi::OsStr, fs::DirEntry, time::SystemTime};
 
 use anyhow::anyhow;
 use globset::GlobSet;
-use regex;
+
+use crate::file_system::entry::FsEntry;
 
 pub enum DirEntryFilter {
     Regex(regex::Regex),
@@ -10,8 +11,7 @@ pub enum DirEntryFilter {
 }
 
 impl DirEntryFilter {
-    pub fn try_match(&self, entry: &DirEntry) -> anyhow::Result<bool> {
-        let name = entry.file_name();
+    pub fn try_match_name(&self, name: &OsStr) -> anyhow::Result<bool> {
         match self {
             Self::Regex(re) => match name.to_str() {
                 Some(s) => Ok(re.is_match(s)),
@@ -20,10 +20,80 @@ impl DirEntryFilter {
                 )),
             },
             Self::Glob { include, exclude } => {
-                let is_match = (include.is_empty() || include.is_match(&name))
+                let is_match = (include.is_empty() || include.is_match(name))
                     && (exclude.is_empty() || !exclude.is_match(name));
                 Ok(is_match)
             }
         }
     }
+
+    /// Matches an entry at any depth of a recursive scan. Directories only have to
+    /// pass the exclude patterns, so that matching files inside them are still reached.
+    pub fn try_match_recursive(&self, entry: &DirEntry) -> anyhow::Result<bool> {
+        self.try_match_name_recursive(&entry.file_name(), entry.file_type()?.is_dir())
+    }
+
+    pub fn try_match_name_recursive(&self, name: &OsStr, is_dir: bool) -> anyhow::Result<bool> {
+        if !is_dir {
+            return self.try_match_name(name);
+        }
+        match self {
+            Self::Regex(_) => Ok(true),
+            Self::Glob { exclude, .. } => Ok(exclude.is_empty() || !exclude.is_match(name)),
+        }
+    }
+}
+
+/// Inclusive bounds on the size (in bytes), line count and modification time of an entry.
+#[derive(Clone, Copy, Default)]
+pub struct SizeFilter {
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub min_lines: Option<u64>,
+    pub max_lines: Option<u64>,
+    pub modified_before: Option<SystemTime>,
+    pub modified_after: Option<SystemTime>,
+}
+
+impl SizeFilter {
+    pub fn is_empty(&self) -> bool {
+        self.min_size.is_none()
+            && self.max_size.is_none()
+            && !self.needs_lines()
+            && self.modified_before.is_none()
+            && self.modified_after.is_none()
+    }
+
+    pub fn needs_lines(&self) -> bool {
+        self.min_lines.is_some() || self.max_lines.is_some()
+    }
+
+    pub fn matches(&self, fse: &FsEntry) -> bool {
+        if let Some(size) = fse.size() {
+            if self.min_size.is_some_and(|min| size < min)
+                || self.max_size.is_some_and(|max| size > max)
+            {
+                return false;
+            }
+        }
+
+        if self.needs_lines() {
+            let lines = fse.lines().unwrap_or(0);
+            if self.min_lines.is_some_and(|min| lines < min)
+                || self.max_lines.is_some_and(|max| lines > max)
+            {
+                return false;
+            }
+        }
+
+        if let Some(modified) = fse.modified() {
+            if self.modified_before.is_some_and(|before| modified > before)
+                || self.modified_after.is_some_and(|after| modified < after)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
 }
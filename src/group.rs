@@ -0,0 +1,73 @@
+use std::{collections::HashMap, ffi::OsStr, path::Path};
+
+use crate::{config::SortBy, file_system::entry::FsEntry};
+
+/// Files aggregated under a shared key, such as their extension.
+#[derive(Clone, Default)]
+pub struct Group {
+    pub name: String,
+    pub size: u64,
+    pub lines: u64,
+    pub count: u64,
+}
+
+/// Files totalled up by key.
+#[derive(Default)]
+pub struct GroupTotals {
+    groups: HashMap<String, Group>,
+}
+
+impl GroupTotals {
+    pub fn add(&mut self, key: &str, fse: &FsEntry) {
+        let group = self.groups.entry(key.to_string()).or_insert_with(|| Group {
+            name: key.to_string(),
+            ..Default::default()
+        });
+        group.size += fse.size().unwrap_or(0);
+        group.lines += fse.lines().unwrap_or(0);
+        group.count += 1;
+    }
+
+    pub fn merge(&mut self, other: GroupTotals) {
+        for (key, other) in other.groups {
+            let group = self.groups.entry(key).or_insert_with(|| Group {
+                name: other.name.clone(),
+                ..Default::default()
+            });
+            group.size += other.size;
+            group.lines += other.lines;
+            group.count += other.count;
+        }
+    }
+
+    /// Returns every group, largest first.
+    pub fn sorted(&self) -> Vec<Group> {
+        let mut sorted: Vec<Group> = self.groups.values().cloned().collect();
+        sorted.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        sorted
+    }
+}
+
+/// Orders a group chart by name or size when asked to, and keeps its own order (largest
+/// first, or oldest last for ages) otherwise.
+pub fn sort_groups(groups: &mut [Group], sort_by: Option<SortBy>, reverse: bool) {
+    match sort_by {
+        Some(SortBy::Name) => groups.sort_by(|a, b| a.name.cmp(&b.name)),
+        Some(SortBy::Size) => {
+            groups.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)))
+        }
+        Some(SortBy::Type) | Some(SortBy::Modified) | None => {}
+    }
+    if reverse {
+        groups.reverse();
+    }
+}
+
+pub const NO_EXTENSION: &str = "(no extension)";
+
+pub fn extension_key(name: &OsStr) -> String {
+    match Path::new(name).extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy().to_lowercase()),
+        None => NO_EXTENSION.to_string(),
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{file_system::entry::FsEntry, owner::Owner};
+
+/// The `n` largest files at any depth.
+pub struct LargestFiles {
+    n: usize,
+    root: PathBuf,
+    by_lines: bool,
+    // A min-heap, so that the smallest kept file is the one to make room
+    heap: BinaryHeap<Reverse<RankedFile>>,
+}
+
+struct RankedFile {
+    rank: u64,
+    path: PathBuf,
+    size: u64,
+    apparent_size: u64,
+    disk_size: u64,
+    lines: Option<u64>,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    owner: Option<Owner>,
+}
+
+impl LargestFiles {
+    /// Files are ranked by line count when `by_lines` is set, and by size otherwise.
+    pub fn new(n: usize, root: &Path, by_lines: bool) -> Self {
+        Self {
+            n,
+            root: root.to_path_buf(),
+            by_lines,
+            heap: BinaryHeap::with_capacity(n + 1),
+        }
+    }
+
+    /// Returns an empty list that keeps as many files, ranked the same way.
+    pub fn empty(&self) -> Self {
+        Self {
+            n: self.n,
+            root: self.root.clone(),
+            by_lines: self.by_lines,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: &Path, fse: &FsEntry) {
+        let FsEntry::File {
+            size,
+            apparent_size,
+            disk_size,
+            lines,
+            modified,
+            accessed,
+            owner,
+            ..
+        } = fse
+        else {
+            return;
+        };
+
+        let rank = match self.by_lines {
+            true => lines.unwrap_or(0),
+            false => *size,
+        };
+
+        let ranked = RankedFile {
+            rank,
+            path: path.strip_prefix(&self.root).unwrap_or(path).to_path_buf(),
+            size: *size,
+            apparent_size: *apparent_size,
+            disk_size: *disk_size,
+            lines: *lines,
+            modified: *modified,
+            accessed: *accessed,
+            owner: *owner,
+        };
+        self.push(ranked);
+    }
+
+    pub fn merge(&mut self, other: LargestFiles) {
+        for Reverse(ranked) in other.heap {
+            self.push(ranked);
+        }
+    }
+
+    fn push(&mut self, ranked: RankedFile) {
+        let heap = &mut self.heap;
+        if heap.len() >= self.n && heap.peek().is_some_and(|Reverse(min)| *min >= ranked) {
+            return;
+        }
+
+        heap.push(Reverse(ranked));
+        if heap.len() > self.n {
+            heap.pop();
+        }
+    }
+
+    /// Returns the kept files largest first, each named by its path relative to the root.
+    pub fn sorted(self) -> Vec<FsEntry> {
+        let mut sorted: Vec<RankedFile> = self
+            .heap
+            .into_iter()
+            .map(|Reverse(ranked)| ranked)
+            .collect();
+        sorted.sort_by(|a, b| b.cmp(a));
+
+        sorted
+            .into_iter()
+            .map(|ranked| FsEntry::File {
+                name: ranked.path.into_os_string(),
+                size: ranked.size,
+                apparent_size: ranked.apparent_size,
+                disk_size: ranked.disk_size,
+                lines: ranked.lines,
+                modified: ranked.modified,
+                accessed: ranked.accessed,
+                owner: ranked.owner,
+            })
+            .collect()
+    }
+}
+
+impl PartialEq for RankedFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedFile {}
+
+impl PartialOrd for RankedFile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedFile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties go to the earlier path, so that the result doesn't depend on scan order
+        self.rank
+            .cmp(&other.rank)
+            .then_with(|| other.path.cmp(&self.path))
+    }
+}
@@ -1,18 +1,30 @@
+mod age;
+mod aggregate;
 mod cli;
 mod config;
+mod defaults;
+mod diff;
+mod duplicates;
 mod file_system;
 mod filter;
+mod group;
+mod largest;
 mod output;
+mod owner;
+mod snapshot;
 mod stats;
+mod tui;
 mod units;
 mod utils;
 
 use std::{
     env,
+    ffi::OsStr,
     fs::{self, DirEntry},
     io::{self, Write},
     path::Path,
-    time::Instant,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::anyhow;
@@ -24,11 +36,35 @@ use crossterm::{
 use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::{
+    aggregate::Aggregates,
     cli::Args,
-    config::Config,
-    file_system::{entry::sort_entries, read::spawn_readers},
-    output::{chart::print_chart, errors::print_errors, summary::print_summary},
+    config::{Config, OutputFormat},
+    diff::{diff_entries, sort_diff_rows, DiffRow},
+    file_system::{
+        device::DeviceBoundary,
+        entry::{retain_entries, sort_entries, FsEntry},
+        ignore::IgnoreStack,
+        inode::InodeSet,
+        language::LanguageStats,
+        read::{spawn_readers, ReadOptions},
+    },
+    group::sort_groups,
+    output::{
+        chart::{
+            print_chart, print_duplicates_chart, print_group_chart, print_largest_chart,
+            print_owner_charts, print_tree_chart,
+        },
+        diff::{print_diff_chart, print_diff_summary, print_side_by_side_chart},
+        errors::print_errors,
+        json::print_json,
+        languages::print_language_table,
+        notices::print_notices,
+        summary::print_summary,
+        table::print_table,
+    },
+    snapshot::Snapshot,
     stats::ScanStats,
+    tui::browser::browse,
     units::system::UnitSystem,
 };
 
@@ -58,41 +94,231 @@ fn main() -> anyhow::Result<()> {
         return Err(anyhow!("error parsing arguments into Config: {}", err));
     });
 
-    let target_path = Path::new(&config.dir);
+    let mut errors: Vec<anyhow::Error> = Vec::new();
+    let mut stats = ScanStats::default();
+
+    let (dir, resolved_dir, mut results) = match &config.load {
+        Some(path) => {
+            let snapshot = Snapshot::load(path)?;
+            let (dir, resolved_dir) = (snapshot.dir.clone(), snapshot.resolved_path.clone());
+            let results = load_entries(snapshot, &config, &mut stats, &mut errors);
+            (dir, resolved_dir, results)
+        }
+        None => {
+            let results = scan(&config, &config.dir, &mut stats, &mut errors)?;
+            let resolved_dir = resolve_dir(&config.dir, &mut errors);
+            (config.dir.clone(), resolved_dir, results)
+        }
+    };
+
+    for groups in [
+        &mut stats.extensions,
+        &mut stats.ages,
+        &mut stats.users,
+        &mut stats.groups,
+    ] {
+        sort_groups(groups, config.sort_by, config.reverse);
+    }
+
+    if let Some(sort_by) = config.sort_by {
+        let mut stderr = io::stderr();
+
+        write!(stderr, "Sorting {} results...", results.len()).unwrap();
+        stderr.flush().unwrap();
+
+        sort_entries(&mut results, &sort_by, config.reverse);
+
+        crossterm::execute!(stderr, MoveToColumn(0), Clear(ClearType::CurrentLine)).unwrap();
+    } else if config.reverse {
+        results.reverse();
+    }
+
+    if let Some(path) = &config.save {
+        Snapshot::new(&dir, &resolved_dir, &stats, &results, &errors).save(path)?;
+    }
+
+    let comparison = match &config.compare {
+        Some(path) => Some(compare_with(path, &config, &results, &mut errors)?),
+        None => None,
+    };
+
+    let took = start.elapsed();
+
+    if !config.no_errors && !errors.is_empty() {
+        print_errors(&errors);
+    }
+
+    if !stats.skipped_mounts.is_empty() && config.format == OutputFormat::Text {
+        print_notices(
+            "skipped mount points",
+            stats.skipped_mounts.iter().map(|path| path.display()),
+        );
+    }
+
+    if config.interactive {
+        browse(results, &resolved_dir, &config)?;
+    } else if let Some((old_stats, rows)) = &comparison {
+        let total = |stats: &ScanStats| stats.total_units(&config.unit_system);
+        let old_label = config.compare.as_ref().unwrap().to_string_lossy();
+        let new_label = match &config.load {
+            Some(path) => path.to_string_lossy(),
+            None => dir.as_str().into(),
+        };
+
+        print_diff_summary(
+            &old_label,
+            &new_label,
+            &config.unit_system,
+            total(old_stats),
+            total(&stats),
+            rows,
+            errors.len(),
+            took,
+        );
+        if config.compare.as_ref().is_some_and(|path| path.is_dir()) {
+            print_side_by_side_chart(rows, &config.unit_system, config.max_bar_width);
+        } else {
+            print_diff_chart(rows, &config.unit_system, config.max_bar_width);
+        }
+    } else {
+        match config.format {
+            OutputFormat::Text => {
+                print_summary(
+                    &dir,
+                    &resolved_dir,
+                    &config.unit_system,
+                    &stats,
+                    results.len(),
+                    errors.len(),
+                    took,
+                );
+
+                if config.by_ext {
+                    print_group_chart(&stats.extensions, &config.unit_system, config.max_bar_width);
+                } else if config.by_age {
+                    print_group_chart(&stats.ages, &config.unit_system, config.max_bar_width);
+                } else if config.by_owner {
+                    print_owner_charts(
+                        &stats.users,
+                        &stats.groups,
+                        &config.unit_system,
+                        config.max_bar_width,
+                    );
+                } else if config.duplicates {
+                    print_duplicates_chart(
+                        &stats.duplicates,
+                        &config.unit_system,
+                        config.max_bar_width,
+                    );
+                } else if let Some(tree) = &config.tree {
+                    print_tree_chart(
+                        &results,
+                        &config.unit_system,
+                        stats.bar_scale(&config.unit_system).0,
+                        tree,
+                        config.max_bar_width,
+                    );
+                } else {
+                    let (max_units, max_digits) = stats.bar_scale(&config.unit_system);
+                    print_chart(
+                        &results,
+                        &config.unit_system,
+                        max_units,
+                        max_digits,
+                        stats.max_name_len,
+                        config.max_bar_width,
+                    );
+                }
+
+                if config.largest.is_some() {
+                    print_largest_chart(&stats.largest, &config.unit_system, config.max_bar_width);
+                }
+
+                if config.languages {
+                    print_language_table(&stats.languages);
+                }
+            }
+            OutputFormat::Json => print_json(
+                &dir,
+                &resolved_dir,
+                &config.unit_system,
+                &stats,
+                &results,
+                config.max_depth(),
+                errors.len(),
+                took,
+            )?,
+            OutputFormat::Csv | OutputFormat::Tsv => print_table(
+                &results,
+                &config.unit_system,
+                &stats,
+                config.max_depth(),
+                if config.format == OutputFormat::Csv {
+                    ','
+                } else {
+                    '\t'
+                },
+            ),
+        }
+    }
+
+    if !errors.is_empty() {
+        let mut msg = format!("encountered {} error", errors.len());
+        if errors.len() > 1 {
+            msg.push('s');
+        }
+        return Err(anyhow!(msg));
+    }
+    Ok(())
+}
+
+/// Scans the target directory, streaming top-level entries through the size filters.
+fn scan(
+    config: &Config,
+    dir: &str,
+    stats: &mut ScanStats,
+    errors: &mut Vec<anyhow::Error>,
+) -> anyhow::Result<Vec<FsEntry>> {
+    let target_path = Path::new(dir);
     if !target_path.exists() || !target_path.is_dir() {
-        return Err(anyhow!("'{}' is not a valid directory.", config.dir));
+        return Err(anyhow!("'{}' is not a valid directory.", dir));
     }
 
     let mut results = Vec::new();
-    let mut errors: Vec<anyhow::Error> = Vec::new();
-    let mut stats = ScanStats::default();
+
+    let ignore = match config.gitignore {
+        true => Some(IgnoreStack::root(target_path, errors)),
+        false => None,
+    };
 
     let entries: Vec<DirEntry> = fs::read_dir(target_path)?
         .filter_map(|result| match result {
             Ok(entry) => {
-                if let Some(entry_type) = &config.needs_type {
-                    match entry_type.try_match(&entry) {
-                        Ok(true) => { /* continue on */ }
-                        Ok(false) => return None,
-                        Err(err) => {
-                            errors.push(err);
-                            return None;
-                        }
+                if let Some(ignore) = &ignore {
+                    let is_dir = entry.file_type().is_ok_and(|ft| ft.is_dir());
+                    if ignore.is_ignored(&entry.path(), is_dir) {
+                        return None;
                     }
                 }
 
-                if let Some(filter) = &config.filter {
-                    match filter.try_match(&entry) {
-                        Ok(true) => { /* continue on */ }
-                        Ok(false) => return None,
-                        Err(err) => {
-                            errors.push(err);
-                            return None;
-                        }
-                    }
+                let file_type = ok_or!(entry.file_type(), err => {
+                    errors.push(anyhow!(
+                        "error getting file type for '{}': {err}",
+                        entry.file_name().to_string_lossy(),
+                    ));
+                    return None;
+                });
+                let name = entry.file_name();
+                match matches_name_filters(
+                    config,
+                    &name,
+                    file_type.is_dir(),
+                    file_type.is_file(),
+                    errors,
+                ) {
+                    true => Some(entry),
+                    false => None,
                 }
-
-                Some(entry)
             }
             Err(err) => {
                 errors.push(anyhow!("error reading dir entry: {}", err));
@@ -112,26 +338,58 @@ fn main() -> anyhow::Result<()> {
                 .progress_chars("█░ "),
         );
 
+        let hard_links = (!config.count_links).then(|| {
+            Arc::new(match config.follow_links {
+                // Like du -L, a file reached through a symlink is only counted once
+                true => InodeSet::every_file(),
+                false => InodeSet::default(),
+            })
+        });
+        let visited_dirs = match config.follow_links {
+            true => {
+                // The root itself counts as visited so that links back to it are not followed
+                let visited_dirs = InodeSet::default();
+                visited_dirs.visit(&fs::metadata(target_path)?);
+                Some(Arc::new(visited_dirs))
+            }
+            false => None,
+        };
+        let aggregates = Aggregates::new(config, target_path, SystemTime::now());
+        let mut totals = aggregates.empty();
+        let languages = config.languages.then(|| Arc::new(LanguageStats::default()));
+        let device_boundary = match config.one_file_system {
+            true => Some(Arc::new(DeviceBoundary::new(&fs::metadata(target_path)?))),
+            false => None,
+        };
+
         let (rx, handles) = spawn_readers(
             entries,
             config.max_threads,
-            config.unit_system == UnitSystem::Lines,
+            ReadOptions {
+                count_lines: config.unit_system == UnitSystem::Lines
+                    || config.size_filter.is_some_and(|f| f.needs_lines()),
+                disk_usage: config.disk_usage,
+                keep_children: config.keep_children(),
+                hard_links: hard_links.clone(),
+                device_boundary: device_boundary.clone(),
+                visited_dirs,
+                filter: config.filter.clone().filter(|_| config.filter_recursive),
+                ignore,
+                file_filter: config.size_filter.filter(|_| config.filter_recursive),
+                languages: languages.clone(),
+                aggregates: config.aggregates_files().then(|| Arc::new(aggregates)),
+            },
         );
 
-        for (fse, errs) in rx {
+        for (fse, entry_totals, errs) in rx {
             pb.inc(1);
 
-            if let Some(size) = fse.size() {
-                if let Some(min_size) = config.min_size {
-                    if size < min_size {
-                        continue;
-                    }
-                }
-                if let Some(max_size) = config.max_size {
-                    if size > max_size {
-                        continue;
-                    }
-                }
+            if !matches_size_filter(config, &fse) {
+                continue;
+            }
+
+            if let Some(entry_totals) = entry_totals {
+                totals.merge(entry_totals);
             }
 
             stats.apply_entry(&fse);
@@ -157,67 +415,176 @@ fn main() -> anyhow::Result<()> {
 
         pb.finish_and_clear();
 
-        if let Some(sort_by) = config.sort_by {
-            let mut stderr = io::stderr();
+        if let Some(hard_links) = &hard_links {
+            stats.duplicate_links = hard_links.duplicates();
+        }
+        if let Some(device_boundary) = &device_boundary {
+            stats.skipped_mounts = device_boundary.skipped();
+        }
+        if let Some(languages) = &languages {
+            stats.languages = languages.sorted();
+        }
+        totals.finish(stats, errors);
+    }
 
-            write!(stderr, "Sorting {} results...", results.len()).unwrap();
-            stderr.flush().unwrap();
+    Ok(results)
+}
 
-            sort_entries(&mut results, &sort_by, config.reverse);
+/// Applies the type and name filters to a top-level entry, whether it is being scanned
+/// or was loaded from a snapshot.
+fn matches_name_filters(
+    config: &Config,
+    name: &OsStr,
+    is_dir: bool,
+    is_file: bool,
+    errors: &mut Vec<anyhow::Error>,
+) -> bool {
+    if let Some(entry_type) = &config.needs_type {
+        if !entry_type.matches(is_dir, is_file) {
+            return false;
+        }
+    }
 
-            crossterm::execute!(stderr, MoveToColumn(0), Clear(ClearType::CurrentLine)).unwrap();
-        } else if config.reverse {
-            results.reverse();
+    if let Some(filter) = &config.filter {
+        let is_match = match config.filter_recursive {
+            true => filter.try_match_name_recursive(name, is_dir),
+            false => filter.try_match_name(name),
+        };
+        match is_match {
+            Ok(true) => { /* continue on */ }
+            Ok(false) => return false,
+            Err(err) => {
+                errors.push(err);
+                return false;
+            }
         }
     }
 
-    let resolved_dir: &str = match fs::canonicalize(Path::new(&config.dir)) {
-        Ok(path) => &format!("{}", path.to_str().unwrap_or(&config.dir)),
+    true
+}
+
+/// Applies the size filters to a top-level entry once it has been read in full.
+fn matches_size_filter(config: &Config, fse: &FsEntry) -> bool {
+    let Some(size_filter) = &config.size_filter else {
+        return true;
+    };
+    // In recursive mode the bounds apply to files only, as they do for nested entries
+    let is_exempt = config.filter_recursive && !matches!(fse, FsEntry::File { .. });
+    is_exempt || size_filter.matches(fse)
+}
+
+fn resolve_dir(dir: &str, errors: &mut Vec<anyhow::Error>) -> String {
+    match fs::canonicalize(Path::new(dir)) {
+        Ok(path) => path.to_str().unwrap_or(dir).to_string(),
         Err(err) => {
-            errors.push(anyhow!(
-                "error resolving full path for '{}': {}",
-                config.dir,
-                err
-            ));
-            &config.dir
+            errors.push(anyhow!("error resolving full path for '{}': {}", dir, err));
+            dir.to_string()
         }
+    }
+}
+
+/// Reads the entries of a saved snapshot, applying the same filters that a scan would.
+fn load_entries(
+    mut snapshot: Snapshot,
+    config: &Config,
+    stats: &mut ScanStats,
+    errors: &mut Vec<anyhow::Error>,
+) -> Vec<FsEntry> {
+    errors.extend(snapshot.errors.iter().map(|err| anyhow!("{err}")));
+    stats.duplicate_links = snapshot.duplicate_links;
+    stats.skipped_mounts = std::mem::take(&mut snapshot.skipped_mount_points);
+
+    // Ages are measured from when the snapshot was taken
+    let created = match snapshot.created {
+        Some(secs) => UNIX_EPOCH + Duration::from_secs(secs),
+        None => SystemTime::now(),
     };
+    let mut aggregates = Aggregates::new(config, Path::new(""), created);
 
-    let took = start.elapsed();
+    let mut results = Vec::new();
 
-    if !config.no_errors && !errors.is_empty() {
-        print_errors(&errors);
+    for mut fse in snapshot.take_entries(config.disk_usage) {
+        let (is_dir, is_file) = (
+            matches!(fse, FsEntry::Dir { .. }),
+            matches!(fse, FsEntry::File { .. }),
+        );
+        if !matches_name_filters(config, fse.name(), is_dir, is_file, errors) {
+            continue;
+        }
+
+        if config.filter_recursive {
+            let mut keep = |child: &FsEntry| {
+                if let Some(filter) = &config.filter {
+                    match filter.try_match_name_recursive(
+                        child.name(),
+                        matches!(child, FsEntry::Dir { .. }),
+                    ) {
+                        Ok(true) => { /* continue on */ }
+                        Ok(false) => return false,
+                        Err(err) => {
+                            errors.push(err);
+                            return false;
+                        }
+                    }
+                }
+                match (child, &config.size_filter) {
+                    (FsEntry::File { .. }, Some(size_filter)) => size_filter.matches(child),
+                    _ => true,
+                }
+            };
+            if let Some(children) = fse.children_mut() {
+                let removed = retain_entries(children, &mut keep);
+                for child in &removed {
+                    fse.subtract(child);
+                }
+            }
+        }
+
+        if !matches_size_filter(config, &fse) {
+            continue;
+        }
+
+        aggregates.add_entry(&fse, Path::new(fse.name()));
+
+        if !config.keep_children() {
+            fse.drop_children();
+        }
+
+        stats.apply_entry(&fse);
+
+        results.push(fse);
     }
 
-    print_summary(
-        &config.dir,
-        resolved_dir,
-        &config.unit_system,
-        stats.total_size,
-        stats.total_lines,
-        stats.dir_count,
-        stats.file_count,
-        stats.unknown_count,
-        results.len(),
-        errors.len(),
-        took,
-    );
+    aggregates.finish(stats, errors);
+
+    results
+}
+
+/// Matches the entries of a scan against those of an earlier snapshot, or of another
+/// directory scanned right away, filtered the same way. Returns the stats of what was
+/// compared against, along with one row per entry.
+fn compare_with(
+    path: &Path,
+    config: &Config,
+    results: &[FsEntry],
+    errors: &mut Vec<anyhow::Error>,
+) -> anyhow::Result<(ScanStats, Vec<DiffRow>)> {
+    let mut old_stats = ScanStats::default();
+    let old_results = if path.is_dir() {
+        scan(config, &path.to_string_lossy(), &mut old_stats, errors)?
+    } else {
+        let snapshot = Snapshot::load(path)?;
+        // Errors hit by the earlier scan were reported when it ran
+        load_entries(snapshot, config, &mut old_stats, &mut Vec::new())
+    };
 
-    print_chart(
-        &results,
+    let mut rows = diff_entries(
+        &old_results,
+        results,
         &config.unit_system,
-        stats.max_size,
-        stats.max_size_digits,
-        stats.max_name_len,
-        config.max_bar_width,
+        config.max_depth(),
     );
+    sort_diff_rows(&mut rows, config.sort_by, config.reverse);
 
-    if !errors.is_empty() {
-        let mut msg = format!("encountered {} error", errors.len());
-        if errors.len() > 1 {
-            msg.push('s');
-        }
-        return Err(anyhow!(msg));
-    }
-    Ok(())
+    Ok((old_stats, rows))
 }
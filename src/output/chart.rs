@@ -3,10 +3,31 @@ use std::collections::HashMap;
 use console;
 use once_cell::sync::Lazy;
 
-use crate::{file_system::entry::FsEntry, units::system::UnitSystem};
+use crate::{
+    config::{BarScale, TreeView},
+    duplicates::DuplicateGroup,
+    file_system::entry::FsEntry,
+    group::Group,
+    units::system::UnitSystem,
+};
+
+static RIGHT_ALIGNS: Lazy<HashMap<UnitSystem, usize>> = Lazy::new(|| {
+    let mut map = HashMap::new();
+
+    map.insert(UnitSystem::Raw, 0);
+
+    let max_len = |units: &[&str]| units.iter().map(|u| u.len()).max().unwrap_or(0);
+    map.insert(UnitSystem::SI, max_len(&UnitSystem::SI_UNITS) + 1);
+    map.insert(UnitSystem::Binary, max_len(&UnitSystem::BINARY_UNITS) + 1);
+
+    map.insert(UnitSystem::Lines, UnitSystem::LINES.len() + 1);
+    map.insert(UnitSystem::Count, UnitSystem::ENTRIES.len() + 1);
+
+    map
+});
 
 pub fn print_chart(
-    entries: &Vec<FsEntry>,
+    entries: &[FsEntry],
     unit_system: &UnitSystem,
     max_size: u64,
     max_size_digits: usize,
@@ -27,60 +48,342 @@ pub fn print_chart(
 }
 
 pub fn make_chart(
-    entries: &Vec<FsEntry>,
+    entries: &[FsEntry],
     unit_system: &UnitSystem,
     max_size: u64,
     max_size_digits: usize,
     max_name_len: usize,
     max_bar_width: u32,
 ) -> String {
-    let max_bar_width_f64 = max_bar_width as f64;
-    let max_size_f64 = max_size as f64;
-
     let mut chart = String::new();
 
     for fse in entries {
-        let size = fse.size().unwrap_or(0);
+        let bar_len = bar_len(
+            unit_system.units_of(fse).unwrap_or(0),
+            max_size,
+            max_bar_width,
+        );
 
-        let mut bar_len = if max_size == 0 {
-            0
-        } else {
-            ((size as f64 / max_size_f64) * max_bar_width_f64).round() as usize
+        let colored_name = colored_name(fse);
+        let name = console::pad_str(&colored_name, max_name_len, console::Alignment::Left, None);
+
+        chart.push_str(&format!(
+            "{name}   [{bar:<bar_width$}]   {size:>size_width$}\n",
+            bar = "#".repeat(bar_len),
+            bar_width = max_bar_width as usize,
+            size = unit_system.format_entry(fse),
+            size_width = max_size_digits + *RIGHT_ALIGNS.get(unit_system).unwrap(),
+        ));
+    }
+
+    chart
+}
+
+pub fn print_tree_chart(
+    entries: &[FsEntry],
+    unit_system: &UnitSystem,
+    max_size: u64,
+    tree: &TreeView,
+    max_bar_width: u32,
+) {
+    print!(
+        "{}",
+        make_tree_chart(entries, unit_system, max_size, tree, max_bar_width)
+    );
+}
+
+pub fn make_tree_chart(
+    entries: &[FsEntry],
+    unit_system: &UnitSystem,
+    max_size: u64,
+    tree: &TreeView,
+    max_bar_width: u32,
+) -> String {
+    let mut rows = Vec::new();
+    collect_tree_rows(
+        entries,
+        unit_system,
+        max_size,
+        tree,
+        max_bar_width,
+        String::new(),
+        1,
+        &mut rows,
+    );
+
+    let max_name_len = rows
+        .iter()
+        .map(|row| console::measure_text_width(&row.name))
+        .max()
+        .unwrap_or(0);
+    let max_size_len = rows.iter().map(|row| row.size.len()).max().unwrap_or(0);
+
+    let mut chart = String::new();
+
+    for row in rows {
+        let name = console::pad_str(&row.name, max_name_len, console::Alignment::Left, None);
+
+        chart.push_str(&format!(
+            "{name}   [{bar:<bar_width$}]   {size:>size_width$}\n",
+            bar = "#".repeat(row.bar_len),
+            bar_width = max_bar_width as usize,
+            size = row.size,
+            size_width = max_size_len,
+        ));
+    }
+
+    chart
+}
+
+struct TreeRow {
+    name: String,
+    size: String,
+    bar_len: usize,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_tree_rows(
+    entries: &[FsEntry],
+    unit_system: &UnitSystem,
+    scale_to: u64,
+    tree: &TreeView,
+    max_bar_width: u32,
+    prefix: String,
+    depth: usize,
+    rows: &mut Vec<TreeRow>,
+) {
+    for (i, fse) in entries.iter().enumerate() {
+        let is_last = i == entries.len() - 1;
+        let (branch, child_prefix) = match (depth, is_last) {
+            (1, _) => ("", String::new()),
+            (_, false) => ("├── ", format!("{prefix}│   ")),
+            (_, true) => ("└── ", format!("{prefix}    ")),
         };
-        if size > 0 && bar_len == 0 {
-            bar_len = 1;
+
+        let size = unit_system.units_of(fse).unwrap_or(0);
+
+        rows.push(TreeRow {
+            name: format!("{prefix}{branch}{}", colored_name(fse)),
+            size: unit_system.format_entry(fse),
+            bar_len: bar_len(size, scale_to, max_bar_width),
+        });
+
+        if tree.max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+
+        if let Some(children) = fse.children() {
+            let child_scale_to = match tree.scale {
+                BarScale::Root => scale_to,
+                BarScale::Parent => size,
+            };
+            collect_tree_rows(
+                children,
+                unit_system,
+                child_scale_to,
+                tree,
+                max_bar_width,
+                child_prefix,
+                depth + 1,
+                rows,
+            );
         }
+    }
+}
+
+pub fn print_group_chart(groups: &[Group], unit_system: &UnitSystem, max_bar_width: u32) {
+    print!("{}", make_group_chart(groups, unit_system, max_bar_width));
+}
 
-        let raw_name = fse.name_str();
-        let colored_name = match fse {
-            FsEntry::File { .. } => raw_name,
-            FsEntry::Dir { .. } => &format!("\x1b[34m{}\x1b[0m", raw_name), // Blue,
-            FsEntry::Unknown { .. } => &format!("\x1b[31m{}\x1b[0m", raw_name), // Red
+/// Renders one bar per group, followed by its file count and share of the total.
+pub fn make_group_chart(groups: &[Group], unit_system: &UnitSystem, max_bar_width: u32) -> String {
+    let units = |group: &Group| match unit_system {
+        UnitSystem::Lines => group.lines,
+        UnitSystem::Count => group.count,
+        _ => group.size,
+    };
+
+    let total: u64 = groups.iter().map(units).sum();
+    let max_units = groups.iter().map(units).max().unwrap_or(0);
+    let max_name_len = groups.iter().map(|g| g.name.len()).max().unwrap_or(0);
+    let max_size_len = groups
+        .iter()
+        .map(|g| unit_system.format(units(g)).len())
+        .max()
+        .unwrap_or(0);
+    let max_count_len = groups
+        .iter()
+        .map(|g| g.count.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    let mut chart = String::new();
+
+    for group in groups {
+        let percent = match total {
+            0 => 0.0,
+            t => units(group) as f64 / t as f64 * 100.0,
         };
-        let name = console::pad_str(&colored_name, max_name_len, console::Alignment::Left, None);
 
-        static RIGHT_ALIGNS: Lazy<HashMap<UnitSystem, usize>> = Lazy::new(|| {
-            let mut map = HashMap::new();
+        chart.push_str(&format!(
+            "{name:<name_width$}   [{bar:<bar_width$}]   {size:>size_width$}   {count:>count_width$} {files}   {percent:>6.2}%\n",
+            name = group.name,
+            name_width = max_name_len,
+            bar = "#".repeat(bar_len(units(group), max_units, max_bar_width)),
+            bar_width = max_bar_width as usize,
+            size = unit_system.format(units(group)),
+            size_width = max_size_len,
+            count = group.count,
+            count_width = max_count_len,
+            files = if group.count == 1 { "file " } else { "files" },
+        ));
+    }
+
+    chart
+}
+
+pub fn print_owner_charts(
+    users: &[Group],
+    groups: &[Group],
+    unit_system: &UnitSystem,
+    max_bar_width: u32,
+) {
+    print!(
+        "{}",
+        make_owner_charts(users, groups, unit_system, max_bar_width)
+    );
+}
+
+/// Renders the totals per user and per group as two group charts under their own headings.
+pub fn make_owner_charts(
+    users: &[Group],
+    groups: &[Group],
+    unit_system: &UnitSystem,
+    max_bar_width: u32,
+) -> String {
+    format!(
+        "Users:\n{}\nGroups:\n{}",
+        make_group_chart(users, unit_system, max_bar_width),
+        make_group_chart(groups, unit_system, max_bar_width)
+    )
+}
 
-            map.insert(UnitSystem::Raw, 0);
+pub fn print_largest_chart(files: &[FsEntry], unit_system: &UnitSystem, max_bar_width: u32) {
+    print!("{}", make_largest_chart(files, unit_system, max_bar_width));
+}
 
-            let max_len = |units: &[&str]| units.iter().map(|u| u.len()).max().unwrap_or(0);
-            map.insert(UnitSystem::SI, max_len(&UnitSystem::SI_UNITS) + 1);
-            map.insert(UnitSystem::Binary, max_len(&UnitSystem::BINARY_UNITS) + 1);
+/// Renders the largest files under a heading, with bars scaled to the first (largest) one.
+pub fn make_largest_chart(
+    files: &[FsEntry],
+    unit_system: &UnitSystem,
+    max_bar_width: u32,
+) -> String {
+    let units = |fse: &FsEntry| unit_system.units_of(fse).unwrap_or(0);
 
-            map.insert(UnitSystem::Lines, UnitSystem::LINES.len() + 1);
+    let max_units = files.iter().map(units).max().unwrap_or(0);
+    let max_name_len = files.iter().map(|f| f.label().len()).max().unwrap_or(0);
+    let max_size_len = files
+        .iter()
+        .map(|f| unit_system.format_entry(f).len())
+        .max()
+        .unwrap_or(0);
 
-            map
-        });
+    let mut chart = String::from("\nLargest files:\n");
 
+    for fse in files {
         chart.push_str(&format!(
-            "{name}   [{bar:<bar_width$}]   {size:>size_width$}\n",
-            bar = "#".repeat(bar_len),
+            "{name:<name_width$}   [{bar:<bar_width$}]   {size:>size_width$}\n",
+            name = fse.label(),
+            name_width = max_name_len,
+            bar = "#".repeat(bar_len(units(fse), max_units, max_bar_width)),
             bar_width = max_bar_width as usize,
             size = unit_system.format_entry(fse),
-            size_width = max_size_digits + *RIGHT_ALIGNS.get(unit_system).unwrap(),
+            size_width = max_size_len,
+        ));
+    }
+
+    chart
+}
+
+pub fn print_duplicates_chart(
+    groups: &[DuplicateGroup],
+    unit_system: &UnitSystem,
+    max_bar_width: u32,
+) {
+    print!(
+        "{}",
+        make_duplicates_chart(groups, unit_system, max_bar_width)
+    );
+}
+
+/// Renders one bar per set of identical files, scaled by the space wasted on copies,
+/// followed by the paths of every copy.
+pub fn make_duplicates_chart(
+    groups: &[DuplicateGroup],
+    unit_system: &UnitSystem,
+    max_bar_width: u32,
+) -> String {
+    if groups.is_empty() {
+        return String::from("No duplicate files found\n");
+    }
+
+    let total_wasted: u64 = groups.iter().map(|g| g.wasted()).sum();
+    let max_wasted = groups.iter().map(|g| g.wasted()).max().unwrap_or(0);
+    let labels: Vec<String> = groups
+        .iter()
+        .map(|g| format!("{} x {}", g.paths.len(), unit_system.format(g.size)))
+        .collect();
+    let max_name_len = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let max_size_len = groups
+        .iter()
+        .map(|g| unit_system.format(g.wasted()).len())
+        .max()
+        .unwrap_or(0);
+
+    let mut chart = format!(
+        "Duplicate files: {} {}, {} wasted\n",
+        groups.len(),
+        if groups.len() == 1 { "set" } else { "sets" },
+        unit_system.format(total_wasted)
+    );
+
+    for (group, label) in groups.iter().zip(&labels) {
+        chart.push_str(&format!(
+            "\n{label:<name_width$}   [{bar:<bar_width$}]   {size:>size_width$} wasted\n",
+            name_width = max_name_len,
+            bar = "#".repeat(bar_len(group.wasted(), max_wasted, max_bar_width)),
+            bar_width = max_bar_width as usize,
+            size = unit_system.format(group.wasted()),
+            size_width = max_size_len,
         ));
+        for path in &group.paths {
+            chart.push_str(&format!("    {}\n", path.to_string_lossy()));
+        }
     }
 
     chart
 }
+
+/// What a bar in the main and tree charts measures: entries in count mode, and size
+/// in every other mode.
+fn colored_name(fse: &FsEntry) -> String {
+    let raw_name = fse.label();
+    match fse {
+        FsEntry::File { .. } => raw_name,
+        FsEntry::Dir { .. } => format!("\x1b[34m{}\x1b[0m", raw_name), // Blue
+        FsEntry::Symlink { .. } => format!("\x1b[36m{}\x1b[0m", raw_name), // Cyan
+        FsEntry::Unknown { .. } => format!("\x1b[31m{}\x1b[0m", raw_name), // Red
+    }
+}
+
+pub(crate) fn bar_len(size: u64, scale_to: u64, max_bar_width: u32) -> usize {
+    let mut bar_len = if scale_to == 0 {
+        0
+    } else {
+        ((size as f64 / scale_to as f64) * max_bar_width as f64).round() as usize
+    };
+    if size > 0 && bar_len == 0 {
+        bar_len = 1;
+    }
+    bar_len
+}
@@ -0,0 +1,222 @@
+use std::time::Duration;
+
+use crate::{
+    diff::{Change, DiffRow},
+    output::chart::bar_len,
+    units::system::UnitSystem,
+};
+
+#[allow(clippy::too_many_arguments)]
+pub fn print_diff_summary(
+    old_label: &str,
+    new_label: &str,
+    unit_system: &UnitSystem,
+    old_total: u64,
+    new_total: u64,
+    rows: &[DiffRow],
+    errors_len: usize,
+    took: Duration,
+) {
+    print!(
+        "{}",
+        make_diff_summary(
+            old_label,
+            new_label,
+            unit_system,
+            old_total,
+            new_total,
+            rows,
+            errors_len,
+            took,
+        )
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn make_diff_summary(
+    old_label: &str,
+    new_label: &str,
+    unit_system: &UnitSystem,
+    old_total: u64,
+    new_total: u64,
+    rows: &[DiffRow],
+    errors_len: usize,
+    took: Duration,
+) -> String {
+    let mut summary = String::new();
+    let mut max_len = 0;
+    let mut push = |s: &str| {
+        if s.len() > max_len {
+            max_len = s.len();
+        }
+        summary.push_str(s);
+    };
+
+    push(&format!(
+        "Size Changes from '{old_label}' to '{new_label}'\n"
+    ));
+    push(&format!("Before: {}\n", unit_system.format(old_total)));
+    push(&format!("After: {}\n", unit_system.format(new_total)));
+
+    let net = new_total as i128 - old_total as i128;
+    let mut net_change = format!("Net Change: {}", format_delta(net, unit_system));
+    if old_total > 0 {
+        net_change.push_str(&format!(
+            " ({:+.2}%)",
+            net as f64 / old_total as f64 * 100.0
+        ));
+    }
+    net_change.push('\n');
+    push(&net_change);
+
+    let count = |change: Change| rows.iter().filter(|row| row.change() == change).count();
+    push(&format!(
+        "Entries: {} grown, {} shrunk, {} new, {} deleted, {} unchanged\n",
+        count(Change::Grown),
+        count(Change::Shrunk),
+        count(Change::New),
+        count(Change::Deleted),
+        count(Change::Unchanged),
+    ));
+
+    push(&format!("Errors: {}\n", errors_len));
+    push(&format!("Took: {:.2?}\n", took));
+
+    let sep = "=".repeat(max_len);
+    format!("{}\n{}{}\n\n", sep, summary, sep)
+}
+
+pub fn print_diff_chart(rows: &[DiffRow], unit_system: &UnitSystem, max_bar_width: u32) {
+    print!("{}", make_diff_chart(rows, unit_system, max_bar_width));
+}
+
+/// Renders one bar per changed entry, scaled to the biggest change. Growth is shown in red
+/// and shrinkage in green, followed by the sizes before and after.
+pub fn make_diff_chart(rows: &[DiffRow], unit_system: &UnitSystem, max_bar_width: u32) -> String {
+    let rows: Vec<&DiffRow> = rows
+        .iter()
+        .filter(|row| row.change() != Change::Unchanged)
+        .collect();
+
+    let max_delta = rows
+        .iter()
+        .map(|row| row.delta().unsigned_abs())
+        .max()
+        .unwrap_or(0);
+    let labels: Vec<String> = rows.iter().map(|row| label(row)).collect();
+    let max_name_len = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let deltas: Vec<String> = rows
+        .iter()
+        .map(|row| format_delta(row.delta(), unit_system))
+        .collect();
+    let max_delta_len = deltas.iter().map(|d| d.len()).max().unwrap_or(0);
+
+    let mut chart = String::new();
+
+    for ((row, label), delta) in rows.iter().zip(&labels).zip(&deltas) {
+        let bar_len = bar_len(
+            row.delta().unsigned_abs() as u64,
+            max_delta as u64,
+            max_bar_width,
+        );
+        let (bar_char, color) = match row.delta() {
+            d if d > 0 => ('+', "31"), // Red
+            _ => ('-', "32"),          // Green
+        };
+        let format_side = |units: Option<u64>| match units {
+            Some(units) => unit_system.format(units),
+            None => "-".to_string(),
+        };
+
+        chart.push_str(&format!(
+            "{label:<name_width$}   [\x1b[{color}m{bar:<bar_width$}\x1b[0m]   \x1b[{color}m{delta:>delta_width$}\x1b[0m   ({} -> {})\n",
+            format_side(row.old),
+            format_side(row.new),
+            name_width = max_name_len,
+            bar = bar_char.to_string().repeat(bar_len),
+            bar_width = max_bar_width as usize,
+            delta_width = max_delta_len,
+        ));
+    }
+
+    chart
+}
+
+fn label(row: &DiffRow) -> String {
+    let path = row.path.to_string_lossy();
+    match row.change() {
+        Change::New => format!("{path} (new)"),
+        Change::Deleted => format!("{path} (deleted)"),
+        _ => path.into_owned(),
+    }
+}
+
+fn format_delta(delta: i128, unit_system: &UnitSystem) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    format!("{sign}{}", unit_system.format(delta.unsigned_abs() as u64))
+}
+
+pub fn print_side_by_side_chart(rows: &[DiffRow], unit_system: &UnitSystem, max_bar_width: u32) {
+    print!(
+        "{}",
+        make_side_by_side_chart(rows, unit_system, max_bar_width)
+    );
+}
+
+/// Renders two bars per entry, one for each side of the comparison, scaled to the largest
+/// entry on either side and followed by the difference between them.
+pub fn make_side_by_side_chart(
+    rows: &[DiffRow],
+    unit_system: &UnitSystem,
+    max_bar_width: u32,
+) -> String {
+    // Both bars together take up about as much room as a single bar in other charts
+    let bar_width = (max_bar_width / 2).max(1);
+
+    let max_units = rows
+        .iter()
+        .flat_map(|row| [row.old, row.new])
+        .flatten()
+        .max()
+        .unwrap_or(0);
+    let labels: Vec<String> = rows.iter().map(label).collect();
+    let max_name_len = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let format_side = |units: Option<u64>| match units {
+        Some(units) => unit_system.format(units),
+        None => "-".to_string(),
+    };
+    let max_size_len = rows
+        .iter()
+        .flat_map(|row| [format_side(row.old).len(), format_side(row.new).len()])
+        .max()
+        .unwrap_or(0);
+
+    let mut chart = String::new();
+
+    for (row, label) in rows.iter().zip(&labels) {
+        let bar =
+            |units: Option<u64>| "#".repeat(bar_len(units.unwrap_or(0), max_units, bar_width));
+        let delta = match row.change() {
+            Change::Unchanged => "=".to_string(),
+            Change::Grown | Change::New => {
+                format!("\x1b[31m{}\x1b[0m", format_delta(row.delta(), unit_system))
+            }
+            Change::Shrunk | Change::Deleted => {
+                format!("\x1b[32m{}\x1b[0m", format_delta(row.delta(), unit_system))
+            }
+        };
+
+        chart.push_str(&format!(
+            "{label:<name_width$}   [{old_bar:<bar_width$}] {old:>size_width$}   [{new_bar:<bar_width$}] {new:>size_width$}   {delta}\n",
+            name_width = max_name_len,
+            old_bar = bar(row.old),
+            old = format_side(row.old),
+            new_bar = bar(row.new),
+            new = format_side(row.new),
+            bar_width = bar_width as usize,
+            size_width = max_size_len,
+        ));
+    }
+
+    chart
+}
@@ -0,0 +1,243 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::{
+    file_system::entry::FsEntry, group::Group, snapshot::to_secs, stats::ScanStats,
+    units::system::UnitSystem,
+};
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    summary: JsonSummary<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    languages: Vec<JsonLanguage<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extensions: Vec<JsonGroup<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ages: Vec<JsonGroup<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    users: Vec<JsonGroup<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<JsonGroup<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    largest: Vec<JsonEntry>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    duplicates: Vec<JsonDuplicates>,
+    entries: Vec<JsonEntry>,
+}
+
+#[derive(Serialize)]
+struct JsonLanguage<'a> {
+    name: &'a str,
+    files: u64,
+    lines: u64,
+    code: u64,
+    comments: u64,
+    blanks: u64,
+}
+
+#[derive(Serialize)]
+struct JsonSummary<'a> {
+    dir: &'a str,
+    resolved_path: &'a str,
+    unit_system: &'static str,
+    total_size: u64,
+    total_apparent_size: u64,
+    total_disk_size: u64,
+    total_lines: u64,
+    total_count: u64,
+    max_size: u64,
+    dir_count: usize,
+    file_count: usize,
+    symlink_count: usize,
+    unknown_count: usize,
+    duplicate_links: usize,
+    skipped_mount_points: Vec<String>,
+    items: usize,
+    error_count: usize,
+    took_secs: f64,
+}
+
+#[derive(Serialize)]
+struct JsonGroup<'a> {
+    name: &'a str,
+    size: u64,
+    lines: u64,
+    count: u64,
+}
+
+impl<'a> From<&'a Group> for JsonGroup<'a> {
+    fn from(group: &'a Group) -> Self {
+        Self {
+            name: &group.name,
+            size: group.size,
+            lines: group.lines,
+            count: group.count,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonDuplicates {
+    size: u64,
+    wasted: u64,
+    paths: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonEntry {
+    name: String,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    size: Option<u64>,
+    apparent_size: Option<u64>,
+    disk_size: Option<u64>,
+    lines: Option<u64>,
+    count: u64,
+    // Seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<JsonEntry>>,
+}
+
+impl JsonEntry {
+    /// Entries nested deeper than `max_depth` are left out, as they are from the chart.
+    fn new(fse: &FsEntry, max_depth: Option<usize>) -> Self {
+        let children = match max_depth {
+            Some(depth) if depth <= 1 => None,
+            _ => fse.children().map(|children| {
+                children
+                    .iter()
+                    .map(|child| Self::new(child, max_depth.map(|depth| depth - 1)))
+                    .collect()
+            }),
+        };
+
+        Self {
+            name: fse.name().to_string_lossy().into_owned(),
+            kind: fse.kind(),
+            target: fse
+                .target()
+                .map(|target| target.to_string_lossy().into_owned()),
+            size: fse.size(),
+            apparent_size: fse.apparent_size(),
+            disk_size: fse.disk_size(),
+            lines: fse.lines(),
+            count: fse.count(),
+            modified: fse.modified().and_then(to_secs),
+            accessed: fse.accessed().and_then(to_secs),
+            children,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn print_json(
+    dir: &str,
+    resolved_dir: &str,
+    unit_system: &UnitSystem,
+    stats: &ScanStats,
+    entries: &[FsEntry],
+    max_depth: Option<usize>,
+    errors_len: usize,
+    took: Duration,
+) -> anyhow::Result<()> {
+    println!(
+        "{}",
+        make_json(
+            dir,
+            resolved_dir,
+            unit_system,
+            stats,
+            entries,
+            max_depth,
+            errors_len,
+            took,
+        )?
+    );
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn make_json(
+    dir: &str,
+    resolved_dir: &str,
+    unit_system: &UnitSystem,
+    stats: &ScanStats,
+    entries: &[FsEntry],
+    max_depth: Option<usize>,
+    errors_len: usize,
+    took: Duration,
+) -> anyhow::Result<String> {
+    let output = JsonOutput {
+        summary: JsonSummary {
+            dir,
+            resolved_path: resolved_dir,
+            unit_system: unit_system.name(),
+            total_size: stats.total_size,
+            total_apparent_size: stats.total_apparent_size,
+            total_disk_size: stats.total_disk_size,
+            total_lines: stats.total_lines,
+            total_count: stats.total_count,
+            max_size: stats.max_size,
+            dir_count: stats.dir_count,
+            file_count: stats.file_count,
+            symlink_count: stats.symlink_count,
+            unknown_count: stats.unknown_count,
+            duplicate_links: stats.duplicate_links,
+            skipped_mount_points: stats
+                .skipped_mounts
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            items: entries.len(),
+            error_count: errors_len,
+            took_secs: took.as_secs_f64(),
+        },
+        languages: stats
+            .languages
+            .iter()
+            .map(|(name, lang)| JsonLanguage {
+                name,
+                files: lang.files,
+                lines: lang.lines(),
+                code: lang.code,
+                comments: lang.comment,
+                blanks: lang.blank,
+            })
+            .collect(),
+        extensions: stats.extensions.iter().map(JsonGroup::from).collect(),
+        ages: stats.ages.iter().map(JsonGroup::from).collect(),
+        users: stats.users.iter().map(JsonGroup::from).collect(),
+        groups: stats.groups.iter().map(JsonGroup::from).collect(),
+        largest: stats
+            .largest
+            .iter()
+            .map(|fse| JsonEntry::new(fse, None))
+            .collect(),
+        duplicates: stats
+            .duplicates
+            .iter()
+            .map(|group| JsonDuplicates {
+                size: group.size,
+                wasted: group.wasted(),
+                paths: group
+                    .paths
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect(),
+            })
+            .collect(),
+        entries: entries
+            .iter()
+            .map(|fse| JsonEntry::new(fse, max_depth))
+            .collect(),
+    };
+
+    Ok(serde_json::to_string_pretty(&output)?)
+}
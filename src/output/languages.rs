@@ -0,0 +1,66 @@
+use crate::file_system::language::LineStats;
+
+const HEADERS: [&str; 6] = ["Language", "Files", "Lines", "Code", "Comments", "Blanks"];
+
+pub fn print_language_table(languages: &[(&str, LineStats)]) {
+    print!("{}", make_language_table(languages));
+}
+
+pub fn make_language_table(languages: &[(&str, LineStats)]) -> String {
+    let mut total = LineStats::default();
+    let mut rows: Vec<[String; 6]> = Vec::new();
+
+    for (name, stats) in languages {
+        total.files += stats.files;
+        total.code += stats.code;
+        total.comment += stats.comment;
+        total.blank += stats.blank;
+        rows.push(make_row(name, stats));
+    }
+    let total_row = make_row("Total", &total);
+
+    let name_width = rows
+        .iter()
+        .chain([&total_row])
+        .map(|row| row[0].len())
+        .max()
+        .unwrap_or(0)
+        .max(HEADERS[0].len());
+    let num_width = rows
+        .iter()
+        .chain([&total_row])
+        .flat_map(|row| row[1..].iter().map(|s| s.len()))
+        .max()
+        .unwrap_or(0)
+        .max(HEADERS.iter().skip(1).map(|h| h.len()).max().unwrap_or(0));
+
+    let format_row = |row: &[String]| {
+        let mut line = format!("{:<name_width$}", row[0]);
+        for cell in &row[1..] {
+            line.push_str(&format!("  {:>num_width$}", cell));
+        }
+        line.push('\n');
+        line
+    };
+
+    let header = format_row(&HEADERS.map(String::from));
+    let sep = "-".repeat(header.len() - 1);
+
+    let mut table = format!("\n{sep}\n{header}{sep}\n");
+    for row in &rows {
+        table.push_str(&format_row(row));
+    }
+    table.push_str(&format!("{sep}\n{}{sep}\n", format_row(&total_row)));
+    table
+}
+
+fn make_row(name: &str, stats: &LineStats) -> [String; 6] {
+    [
+        name.to_string(),
+        stats.files.to_string(),
+        stats.lines().to_string(),
+        stats.code.to_string(),
+        stats.comment.to_string(),
+        stats.blank.to_string(),
+    ]
+}
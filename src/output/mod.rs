@@ -1,3 +1,8 @@
 pub mod chart;
+pub mod diff;
 pub mod errors;
+pub mod json;
+pub mod languages;
+pub mod notices;
 pub mod summary;
+pub mod table;
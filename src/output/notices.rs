@@ -0,0 +1,12 @@
+pub fn print_notices<I>(title: &str, notices: I)
+where
+    I: IntoIterator,
+    I::Item: std::fmt::Display,
+{
+    let title = title.to_uppercase();
+    eprintln!("\n=== START {} ===", title);
+    for notice in notices {
+        eprintln!("{}", notice);
+    }
+    eprintln!("=== END {} ===\n", title);
+}
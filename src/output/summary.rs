@@ -1,16 +1,12 @@
 use std::time::Duration;
 
-use crate::units::system::UnitSystem;
+use crate::{stats::ScanStats, units::system::UnitSystem};
 
 pub fn print_summary(
     dir: impl Into<String>,
     resolved_dir: impl Into<String>,
     unit_system: &UnitSystem,
-    total_size: u64,
-    total_lines: u64,
-    dir_count: usize,
-    file_count: usize,
-    unknown_count: usize,
+    stats: &ScanStats,
     results_len: usize,
     errors_len: usize,
     took: Duration,
@@ -21,11 +17,7 @@ pub fn print_summary(
             dir,
             resolved_dir,
             unit_system,
-            total_size,
-            total_lines,
-            dir_count,
-            file_count,
-            unknown_count,
+            stats,
             results_len,
             errors_len,
             took,
@@ -37,11 +29,7 @@ pub fn make_summary(
     dir: impl Into<String>,
     resolved_dir: impl Into<String>,
     unit_system: &UnitSystem,
-    total_size: u64,
-    total_lines: u64,
-    dir_count: usize,
-    file_count: usize,
-    unknown_count: usize,
+    stats: &ScanStats,
     results_len: usize,
     errors_len: usize,
     took: Duration,
@@ -58,23 +46,47 @@ pub fn make_summary(
     push(&format!("File/Directory Sizes in '{}'\n", dir.into()));
     push(&format!("Resolved Path: {}\n", resolved_dir.into()));
 
-    let units = if unit_system == &UnitSystem::Lines {
-        total_lines
-    } else {
-        total_size
-    };
-    push(&format!("Total Size: {}\n", unit_system.format(units)));
+    let total = unit_system.format(stats.total_units(unit_system));
+    match unit_system {
+        UnitSystem::Count => push(&format!("Total Count: {}\n", total)),
+        _ => push(&format!("Total Size: {}\n", total)),
+    }
+
+    if !matches!(unit_system, UnitSystem::Lines | UnitSystem::Count) {
+        push(&format!(
+            "Apparent Size: {} | Disk Usage: {}\n",
+            unit_system.format(stats.total_apparent_size),
+            unit_system.format(stats.total_disk_size)
+        ));
+    }
 
     let mut items = format!(
         "Items: {} ({} dirs, {} files",
-        results_len, dir_count, file_count
+        results_len, stats.dir_count, stats.file_count
     );
-    if unknown_count > 0 {
-        items.push_str(&format!(", {} unknown", unknown_count));
+    if stats.symlink_count > 0 {
+        items.push_str(&format!(", {} symlinks", stats.symlink_count));
+    }
+    if stats.unknown_count > 0 {
+        items.push_str(&format!(", {} unknown", stats.unknown_count));
     }
     items.push_str(")\n");
     push(&items);
 
+    if stats.duplicate_links > 0 {
+        push(&format!(
+            "Duplicate Hard Links: {} (counted once)\n",
+            stats.duplicate_links
+        ));
+    }
+
+    if !stats.skipped_mounts.is_empty() {
+        push(&format!(
+            "Skipped Mount Points: {}\n",
+            stats.skipped_mounts.len()
+        ));
+    }
+
     push(&format!("Errors: {}\n", errors_len));
     push(&format!("Took: {:.2?}\n", took));
 
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use crate::{file_system::entry::FsEntry, stats::ScanStats, units::system::UnitSystem};
+
+const HEADER: [&str; 6] = ["name", "type", "size_bytes", "size", "lines", "percent"];
+
+pub fn print_table(
+    entries: &[FsEntry],
+    unit_system: &UnitSystem,
+    stats: &ScanStats,
+    max_depth: Option<usize>,
+    delimiter: char,
+) {
+    print!(
+        "{}",
+        make_table(entries, unit_system, stats, max_depth, delimiter)
+    );
+}
+
+/// Renders one delimited row per entry. Nested entries (when children were kept)
+/// are named by their path relative to the scanned directory.
+pub fn make_table(
+    entries: &[FsEntry],
+    unit_system: &UnitSystem,
+    stats: &ScanStats,
+    max_depth: Option<usize>,
+    delimiter: char,
+) -> String {
+    let total = stats.total_units(unit_system);
+
+    let mut table = String::new();
+    push_row(&mut table, &HEADER.map(String::from), delimiter);
+    push_entries(
+        &mut table,
+        entries,
+        unit_system,
+        total,
+        max_depth,
+        Path::new(""),
+        delimiter,
+    );
+    table
+}
+
+fn push_entries(
+    table: &mut String,
+    entries: &[FsEntry],
+    unit_system: &UnitSystem,
+    total: u64,
+    max_depth: Option<usize>,
+    parent: &Path,
+    delimiter: char,
+) {
+    for fse in entries {
+        let path = parent.join(fse.name());
+
+        let percent = match (unit_system.units_of(fse), total) {
+            (Some(n), t) if t > 0 => format!("{:.2}", n as f64 / t as f64 * 100.0),
+            _ => String::new(),
+        };
+
+        push_row(
+            table,
+            &[
+                path.to_string_lossy().into_owned(),
+                fse.kind().to_string(),
+                fse.size().map(|n| n.to_string()).unwrap_or_default(),
+                unit_system.format_entry(fse),
+                fse.lines().map(|n| n.to_string()).unwrap_or_default(),
+                percent,
+            ],
+            delimiter,
+        );
+
+        let depth = path.components().count();
+        if max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+
+        if let Some(children) = fse.children() {
+            push_entries(
+                table,
+                children,
+                unit_system,
+                total,
+                max_depth,
+                &path,
+                delimiter,
+            );
+        }
+    }
+}
+
+fn push_row(table: &mut String, fields: &[String], delimiter: char) {
+    let row: Vec<String> = fields
+        .iter()
+        .map(|field| escape_field(field, delimiter))
+        .collect();
+    table.push_str(&row.join(&delimiter.to_string()));
+    table.push('\n');
+}
+
+fn escape_field(field: &str, delimiter: char) -> String {
+    if delimiter == '\t' {
+        // TSV has no quoting, so control characters are escaped instead
+        return field
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+    }
+
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    collections::HashMap,
+    fs::{self, Metadata},
+    sync::Arc,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::{
+    file_system::entry::FsEntry,
+    group::{Group, GroupTotals},
+};
+
+/// The user and group that own a file.
+#[derive(Clone, Copy)]
+pub struct Owner {
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Owner {
+    #[cfg(unix)]
+    pub fn of(metadata: &Metadata) -> Option<Self> {
+        Some(Self {
+            uid: metadata.uid(),
+            gid: metadata.gid(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn of(_metadata: &Metadata) -> Option<Self> {
+        None
+    }
+}
+
+pub const UNKNOWN_OWNER: &str = "(unknown)";
+
+/// Files totalled by the user and by the group that own them.
+pub struct OwnerTotals {
+    user_names: Arc<HashMap<u32, String>>,
+    group_names: Arc<HashMap<u32, String>>,
+    users: GroupTotals,
+    groups: GroupTotals,
+}
+
+impl OwnerTotals {
+    /// Reads user and group names up front, so that ids are only resolved once.
+    pub fn new() -> Self {
+        Self {
+            user_names: Arc::new(read_names("/etc/passwd")),
+            group_names: Arc::new(read_names("/etc/group")),
+            users: GroupTotals::default(),
+            groups: GroupTotals::default(),
+        }
+    }
+
+    /// Returns empty totals that share the names read up front.
+    pub fn empty(&self) -> Self {
+        Self {
+            user_names: self.user_names.clone(),
+            group_names: self.group_names.clone(),
+            users: GroupTotals::default(),
+            groups: GroupTotals::default(),
+        }
+    }
+
+    pub fn add(&mut self, fse: &FsEntry) {
+        let FsEntry::File { owner, .. } = fse else {
+            return;
+        };
+
+        match owner {
+            Some(owner) => {
+                self.users
+                    .add(&name_or_id(&self.user_names, owner.uid), fse);
+                self.groups
+                    .add(&name_or_id(&self.group_names, owner.gid), fse);
+            }
+            None => {
+                self.users.add(UNKNOWN_OWNER, fse);
+                self.groups.add(UNKNOWN_OWNER, fse);
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: OwnerTotals) {
+        self.users.merge(other.users);
+        self.groups.merge(other.groups);
+    }
+
+    /// Returns the totals per user and per group, largest first.
+    pub fn sorted(&self) -> (Vec<Group>, Vec<Group>) {
+        (self.users.sorted(), self.groups.sorted())
+    }
+}
+
+fn name_or_id(names: &HashMap<u32, String>, id: u32) -> String {
+    match names.get(&id) {
+        Some(name) => name.clone(),
+        None => id.to_string(),
+    }
+}
+
+/// Maps ids to names from a file in the format of /etc/passwd or /etc/group, where the
+/// name and id are the first and third fields. Ids that can't be resolved are shown as is.
+fn read_names(path: &str) -> HashMap<u32, String> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return HashMap::new();
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
@@ -0,0 +1,216 @@
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+use crate::{file_system::entry::FsEntry, owner::Owner, stats::ScanStats};
+
+const VERSION: u32 = 1;
+
+/// A scanned tree saved to disk, so that it can be charted again without rescanning.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    version: u32,
+    pub dir: String,
+    pub resolved_path: String,
+    // Seconds since the Unix epoch
+    pub created: Option<u64>,
+    pub duplicate_links: usize,
+    pub skipped_mount_points: Vec<PathBuf>,
+    pub errors: Vec<String>,
+    entries: Vec<SnapshotEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SnapshotKind {
+    File,
+    Dir,
+    Symlink,
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    name: String,
+    kind: SnapshotKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<PathBuf>,
+    #[serde(default)]
+    apparent_size: u64,
+    #[serde(default)]
+    disk_size: u64,
+    #[serde(default)]
+    lines: Option<u64>,
+    // Seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    accessed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    children: Option<Vec<SnapshotEntry>>,
+}
+
+impl Snapshot {
+    pub fn new(
+        dir: &str,
+        resolved_dir: &str,
+        stats: &ScanStats,
+        entries: &[FsEntry],
+        errors: &[anyhow::Error],
+    ) -> Self {
+        Self {
+            version: VERSION,
+            dir: dir.to_string(),
+            resolved_path: resolved_dir.to_string(),
+            created: to_secs(SystemTime::now()),
+            duplicate_links: stats.duplicate_links,
+            skipped_mount_points: stats.skipped_mounts.clone(),
+            errors: errors.iter().map(|err| err.to_string()).collect(),
+            entries: entries.iter().map(SnapshotEntry::from).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path).map_err(|err| {
+            anyhow!(
+                "error creating snapshot '{}': {err}",
+                path.to_string_lossy()
+            )
+        })?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .map_err(|err| anyhow!("error opening snapshot '{}': {err}", path.to_string_lossy()))?;
+        let snapshot: Self = serde_json::from_reader(BufReader::new(file))
+            .map_err(|err| anyhow!("error reading snapshot '{}': {err}", path.to_string_lossy()))?;
+
+        if snapshot.version != VERSION {
+            return Err(anyhow!(
+                "snapshot '{}' has unsupported version {} (expected {})",
+                path.to_string_lossy(),
+                snapshot.version,
+                VERSION
+            ));
+        }
+        Ok(snapshot)
+    }
+
+    /// Takes the saved tree, charting allocated sizes when `disk_usage` is set.
+    pub fn take_entries(&mut self, disk_usage: bool) -> Vec<FsEntry> {
+        std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|entry| entry.into_fs_entry(disk_usage))
+            .collect()
+    }
+}
+
+impl From<&FsEntry> for SnapshotEntry {
+    fn from(fse: &FsEntry) -> Self {
+        Self {
+            name: fse.name().to_string_lossy().into_owned(),
+            kind: match fse {
+                FsEntry::File { .. } => SnapshotKind::File,
+                FsEntry::Dir { .. } => SnapshotKind::Dir,
+                FsEntry::Symlink { .. } => SnapshotKind::Symlink,
+                FsEntry::Unknown { .. } => SnapshotKind::Unknown,
+            },
+            target: fse.target().cloned(),
+            apparent_size: fse.apparent_size().unwrap_or(0),
+            disk_size: fse.disk_size().unwrap_or(0),
+            lines: fse.lines(),
+            modified: fse.modified().and_then(to_secs),
+            accessed: fse.accessed().and_then(to_secs),
+            uid: fse.owner().map(|owner| owner.uid),
+            gid: fse.owner().map(|owner| owner.gid),
+            children: fse
+                .children()
+                .map(|children| children.iter().map(SnapshotEntry::from).collect()),
+        }
+    }
+}
+
+impl SnapshotEntry {
+    fn into_fs_entry(self, disk_usage: bool) -> FsEntry {
+        let name = OsString::from(self.name);
+        let size = if disk_usage {
+            self.disk_size
+        } else {
+            self.apparent_size
+        };
+        let from_secs = |secs: u64| UNIX_EPOCH + Duration::from_secs(secs);
+        let (modified, accessed) = (self.modified.map(from_secs), self.accessed.map(from_secs));
+        let children: Option<Vec<FsEntry>> = self.children.map(|children| {
+            children
+                .into_iter()
+                .map(|child| child.into_fs_entry(disk_usage))
+                .collect()
+        });
+        // Entry counts aren't saved, since they follow from the saved children
+        let count = 1 + children
+            .iter()
+            .flatten()
+            .map(|child| child.count())
+            .sum::<u64>();
+
+        match self.kind {
+            SnapshotKind::File => FsEntry::File {
+                name,
+                size,
+                apparent_size: self.apparent_size,
+                disk_size: self.disk_size,
+                lines: self.lines,
+                modified,
+                accessed,
+                owner: match (self.uid, self.gid) {
+                    (Some(uid), Some(gid)) => Some(Owner { uid, gid }),
+                    _ => None,
+                },
+            },
+            SnapshotKind::Dir => FsEntry::Dir {
+                name,
+                size,
+                apparent_size: self.apparent_size,
+                disk_size: self.disk_size,
+                lines: self.lines,
+                modified,
+                accessed,
+                count,
+                children,
+            },
+            SnapshotKind::Symlink => FsEntry::Symlink {
+                name,
+                target: self.target.unwrap_or_default(),
+                size,
+                apparent_size: self.apparent_size,
+                disk_size: self.disk_size,
+                lines: self.lines,
+                modified,
+                accessed,
+                count,
+                children,
+            },
+            SnapshotKind::Unknown => FsEntry::Unknown { name },
+        }
+    }
+}
+
+/// Seconds since the Unix epoch, as times are stored in snapshots and JSON output.
+pub fn to_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
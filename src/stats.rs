@@ -1,20 +1,43 @@
-use crate::{file_system::entry::FsEntry, utils::math::count_digits};
+use std::path::PathBuf;
+
+use crate::{
+    duplicates::DuplicateGroup,
+    file_system::{entry::FsEntry, language::LineStats},
+    group::Group,
+    units::system::UnitSystem,
+    utils::math::count_digits,
+};
 
 #[derive(Default)]
 pub struct ScanStats {
     pub total_size: u64,
+    pub total_apparent_size: u64,
+    pub total_disk_size: u64,
     pub total_lines: u64,
+    pub total_count: u64,
     pub max_size: u64,
     pub max_size_digits: usize,
+    pub max_count: u64,
+    pub max_lines: u64,
     pub max_name_len: usize,
     pub dir_count: usize,
     pub file_count: usize,
+    pub symlink_count: usize,
     pub unknown_count: usize,
+    pub duplicate_links: usize,
+    pub skipped_mounts: Vec<PathBuf>,
+    pub languages: Vec<(&'static str, LineStats)>,
+    pub extensions: Vec<Group>,
+    pub ages: Vec<Group>,
+    pub users: Vec<Group>,
+    pub groups: Vec<Group>,
+    pub largest: Vec<FsEntry>,
+    pub duplicates: Vec<DuplicateGroup>,
 }
 
 impl ScanStats {
     pub fn apply_entry(&mut self, fse: &FsEntry) {
-        let name_len = fse.name_str().len();
+        let name_len = fse.label().len();
         if name_len > self.max_name_len {
             self.max_name_len = name_len;
         }
@@ -31,14 +54,50 @@ impl ScanStats {
             }
         }
 
+        if let Some(size) = fse.apparent_size() {
+            self.total_apparent_size += size;
+        }
+        if let Some(size) = fse.disk_size() {
+            self.total_disk_size += size;
+        }
+
         if let Some(lines) = fse.lines() {
             self.total_lines += lines;
+            if lines > self.max_lines {
+                self.max_lines = lines;
+            }
+        }
+
+        self.total_count += fse.count();
+        if fse.count() > self.max_count {
+            self.max_count = fse.count();
         }
 
         match fse {
             FsEntry::File { .. } => self.file_count += 1,
             FsEntry::Dir { .. } => self.dir_count += 1,
+            FsEntry::Symlink { .. } => self.symlink_count += 1,
             FsEntry::Unknown { .. } => self.unknown_count += 1,
         }
     }
+
+    /// The total in the chosen unit system: lines, entries or bytes.
+    pub fn total_units(&self, unit_system: &UnitSystem) -> u64 {
+        match unit_system {
+            UnitSystem::Lines => self.total_lines,
+            UnitSystem::Count => self.total_count,
+            _ => self.total_size,
+        }
+    }
+
+    /// What bars in the main chart are scaled to, along with its number of digits.
+    /// Lines and entries are counted in their own modes, while the others scale bars
+    /// by size.
+    pub fn bar_scale(&self, unit_system: &UnitSystem) -> (u64, usize) {
+        match unit_system {
+            UnitSystem::Lines => (self.max_lines, count_digits(self.max_lines)),
+            UnitSystem::Count => (self.max_count, count_digits(self.max_count)),
+            _ => (self.max_size, self.max_size_digits),
+        }
+    }
 }
@@ -0,0 +1,434 @@
+use std::{
+    env,
+    fs::{self, Metadata, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+
+use crate::file_system::{inode::InodeSet, read::allocated_size};
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Delete,
+    Trash,
+}
+
+impl Action {
+    pub fn verb(&self) -> &'static str {
+        match self {
+            Self::Delete => "Delete",
+            Self::Trash => "Move to trash",
+        }
+    }
+
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            Self::Delete => "Deleted",
+            Self::Trash => "Trashed",
+        }
+    }
+
+    /// Whether the space taken up by removed entries is freed right away, rather than
+    /// once the trash is emptied.
+    pub fn frees_space(&self) -> bool {
+        matches!(self, Self::Delete)
+    }
+
+    pub fn apply(&self, path: &Path, trash: Option<&TrashDir>) -> anyhow::Result<()> {
+        match self {
+            Self::Delete => remove_tree(path, parent_device(path))
+                .map_err(|err| anyhow!("error deleting '{}': {err}", path.to_string_lossy())),
+            Self::Trash => {
+                let trash = trash.ok_or_else(|| {
+                    anyhow!("no trash directory found, pass one with --trash-dir")
+                })?;
+                trash.put(path)
+            }
+        }
+    }
+}
+
+/// Measures what removing `path` would free on disk right now, including entries that
+/// the scan left out. Fails when the entry crosses onto another filesystem, such as a
+/// mount point skipped by -x, or when part of it can't be read, since it then can't be
+/// removed in full.
+pub fn measure(path: &Path, disk_usage: bool) -> anyhow::Result<u64> {
+    let hard_links = InodeSet::default();
+    measure_tree(path, parent_device(path), disk_usage, &hard_links)
+}
+
+fn measure_tree(
+    path: &Path,
+    device: Option<u64>,
+    disk_usage: bool,
+    hard_links: &InodeSet,
+) -> anyhow::Result<u64> {
+    let metadata = fs::symlink_metadata(path)
+        .map_err(|err| anyhow!("error reading '{}': {err}", path.to_string_lossy()))?;
+    check_device(path, &metadata, device)?;
+
+    let mut size = match (metadata.is_file(), disk_usage) {
+        (true, _) if !hard_links.insert(&metadata) => 0,
+        (_, true) => allocated_size(&metadata),
+        (_, false) => metadata.len(),
+    };
+
+    if metadata.is_dir() {
+        let entries = fs::read_dir(path)
+            .map_err(|err| anyhow!("error reading dir '{}': {err}", path.to_string_lossy()))?;
+        for result in entries {
+            let entry = result.map_err(|err| {
+                anyhow!(
+                    "error reading dir entry '{}': {err}",
+                    path.to_string_lossy()
+                )
+            })?;
+            size += measure_tree(&entry.path(), device, disk_usage, hard_links)?;
+        }
+    }
+
+    Ok(size)
+}
+
+/// Removes a file, a symlink (but not what it points to) or a whole directory, without
+/// crossing onto another filesystem than `device`.
+fn remove_tree(path: &Path, device: Option<u64>) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_dir() {
+        return fs::remove_file(path);
+    }
+    if !is_on_device(&metadata, device) {
+        return Err(io::Error::other(format!(
+            "'{}' is on another filesystem",
+            path.to_string_lossy()
+        )));
+    }
+
+    for result in fs::read_dir(path)? {
+        remove_tree(&result?.path(), device)?;
+    }
+    fs::remove_dir(path)
+}
+
+fn check_device(path: &Path, metadata: &Metadata, device: Option<u64>) -> anyhow::Result<()> {
+    match is_on_device(metadata, device) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "'{}' is on another filesystem",
+            path.to_string_lossy()
+        )),
+    }
+}
+
+/// The device of the directory containing `path`, which removing it must stay on.
+fn parent_device(path: &Path) -> Option<u64> {
+    let metadata = fs::metadata(path.parent()?).ok()?;
+    device_of(&metadata)
+}
+
+fn is_on_device(metadata: &Metadata, device: Option<u64>) -> bool {
+    match (device, device_of(metadata)) {
+        (Some(expected), Some(actual)) => expected == actual,
+        _ => true,
+    }
+}
+
+#[cfg(unix)]
+fn device_of(metadata: &Metadata) -> Option<u64> {
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_of(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Where trashed entries are moved to. Entries are only ever renamed into a trash on
+/// their own filesystem, so that trashing them never copies any data.
+pub enum TrashDir {
+    /// Chosen with --trash-dir. Entries on another filesystem are refused.
+    Custom(PathBuf),
+    /// The home trash of the freedesktop.org spec. Entries on another filesystem go to
+    /// the `.Trash-$uid` directory at the top of their own, as the spec asks.
+    Desktop(PathBuf),
+}
+
+impl TrashDir {
+    /// The desktop trash, when the environment points to one.
+    pub fn desktop() -> Option<Self> {
+        let data_dir = match env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+        };
+        Some(Self::Desktop(data_dir.join("Trash")))
+    }
+
+    fn put(&self, path: &Path) -> anyhow::Result<()> {
+        let bin = self.bin_for(path)?;
+
+        let (dest, info_path) = bin.reserve(path)?;
+
+        fs::rename(path, &dest).map_err(|err| {
+            if let Some(info_path) = &info_path {
+                let _ = fs::remove_file(info_path);
+            }
+            anyhow!(
+                "error moving '{}' to '{}': {err}",
+                path.to_string_lossy(),
+                dest.to_string_lossy()
+            )
+        })
+    }
+
+    /// Picks the trash that `path` can be renamed into, creating it when needed.
+    fn bin_for(&self, path: &Path) -> anyhow::Result<Bin> {
+        let device = parent_device(path);
+
+        let bin = match self {
+            Self::Custom(dir) => {
+                create_trash_dir(dir)?;
+                if !is_on_device(&trash_metadata(dir)?, device) {
+                    return Err(anyhow!(
+                        "'{}' is on another filesystem than the trash directory '{}'",
+                        path.to_string_lossy(),
+                        dir.to_string_lossy()
+                    ));
+                }
+                Bin {
+                    files: dir.clone(),
+                    info: None,
+                    top_dir: None,
+                }
+            }
+            Self::Desktop(home) => {
+                create_trash_dir(home)?;
+                match is_on_device(&trash_metadata(home)?, device) {
+                    true => Bin {
+                        files: home.join("files"),
+                        info: Some(home.join("info")),
+                        top_dir: None,
+                    },
+                    false => volume_bin(path)?,
+                }
+            }
+        };
+
+        for dir in [Some(&bin.files), bin.info.as_ref()].into_iter().flatten() {
+            create_trash_dir(dir)?;
+        }
+        Ok(bin)
+    }
+}
+
+/// A trash directory on a single filesystem.
+struct Bin {
+    files: PathBuf,
+    // Set for the desktop trash, which records where each entry came from so that it
+    // can be restored
+    info: Option<PathBuf>,
+    // Set for the trash at the top of a volume, whose info files record paths
+    // relative to it
+    top_dir: Option<PathBuf>,
+}
+
+impl Bin {
+    /// Picks a free name in the trash, keeping entries trashed earlier under the same
+    /// name. For the desktop trash, the name is claimed by creating its info file.
+    fn reserve(&self, path: &Path) -> anyhow::Result<(PathBuf, Option<PathBuf>)> {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        let mut n = 0;
+        loop {
+            let mut numbered = name.to_os_string();
+            if n > 0 {
+                numbered.push(format!(".{n}"));
+            }
+            n += 1;
+
+            let dest = self.files.join(&numbered);
+            if fs::symlink_metadata(&dest).is_ok() {
+                continue;
+            }
+            let Some(info_dir) = &self.info else {
+                return Ok((dest, None));
+            };
+
+            let original = match &self.top_dir {
+                Some(top_dir) => path.strip_prefix(top_dir).unwrap_or(path),
+                None => path,
+            };
+            numbered.push(".trashinfo");
+            let info_path = info_dir.join(numbered);
+            let result = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&info_path)
+                .and_then(|mut file| file.write_all(trash_info(original).as_bytes()));
+            match result {
+                Ok(()) => return Ok((dest, Some(info_path))),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => {
+                    return Err(anyhow!(
+                        "error writing '{}': {err}",
+                        info_path.to_string_lossy()
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// The `$topdir/.Trash-$uid` trash of the filesystem that `path` is on.
+#[cfg(unix)]
+fn volume_bin(path: &Path) -> anyhow::Result<Bin> {
+    let top_dir = top_dir(path).map_err(|err| {
+        anyhow!(
+            "error finding the top of the filesystem of '{}': {err}",
+            path.to_string_lossy()
+        )
+    })?;
+    // SAFETY: getuid can't fail and has no side effects
+    let uid = unsafe { libc::getuid() };
+    let trash = top_dir.join(format!(".Trash-{uid}"));
+
+    create_trash_dir(&trash)?;
+    fs::set_permissions(&trash, std::os::unix::fs::PermissionsExt::from_mode(0o700))
+        .map_err(|err| anyhow!("error securing '{}': {err}", trash.to_string_lossy()))?;
+
+    Ok(Bin {
+        files: trash.join("files"),
+        info: Some(trash.join("info")),
+        top_dir: Some(top_dir),
+    })
+}
+
+#[cfg(not(unix))]
+fn volume_bin(path: &Path) -> anyhow::Result<Bin> {
+    Err(anyhow!(
+        "'{}' is on another filesystem than the trash, pass one on it with --trash-dir",
+        path.to_string_lossy()
+    ))
+}
+
+/// The mount point of the filesystem that `path` is on: the highest directory above
+/// it on the same device.
+#[cfg(unix)]
+fn top_dir(path: &Path) -> io::Result<PathBuf> {
+    let parent = fs::canonicalize(path.parent().unwrap_or(path))?;
+    let device = fs::metadata(&parent)?.dev();
+
+    let mut top_dir = parent.as_path();
+    while let Some(above) = top_dir.parent() {
+        if fs::metadata(above)?.dev() != device {
+            break;
+        }
+        top_dir = above;
+    }
+    Ok(top_dir.to_path_buf())
+}
+
+fn create_trash_dir(dir: &Path) -> anyhow::Result<()> {
+    fs::create_dir_all(dir).map_err(|err| {
+        anyhow!(
+            "error creating trash directory '{}': {err}",
+            dir.to_string_lossy()
+        )
+    })
+}
+
+fn trash_metadata(dir: &Path) -> anyhow::Result<Metadata> {
+    fs::metadata(dir).map_err(|err| {
+        anyhow!(
+            "error reading trash directory '{}': {err}",
+            dir.to_string_lossy()
+        )
+    })
+}
+
+/// The contents of a `.trashinfo` file, recording the original path of an entry.
+/// Paths in the trash at the top of a volume are relative to it.
+fn trash_info(path: &Path) -> String {
+    format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(path),
+        // The spec asks for local time, which isn't known without a timezone database
+        format_utc(SystemTime::now()),
+    )
+}
+
+/// Percent-encodes a path the way URLs are, keeping the separators.
+fn encode_path(path: &Path) -> String {
+    path.as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .map(|&b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Formats a time as `YYYY-MM-DDThh:mm:ss`.
+fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, secs) = ((secs / 86_400) as i64, secs % 86_400);
+
+    // Converts days since 1970-01-01 to a civil date, with years starting in March
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn formats_utc_times() {
+        let at = |secs| format_utc(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01T00:00:00");
+        assert_eq!(at(951_782_400), "2000-02-29T00:00:00");
+        assert_eq!(at(1_709_251_199), "2024-02-29T23:59:59");
+        assert_eq!(at(1_735_689_600), "2025-01-01T00:00:00");
+        assert_eq!(at(4_102_444_799), "2099-12-31T23:59:59");
+    }
+
+    #[test]
+    fn encodes_paths() {
+        assert_eq!(
+            encode_path(Path::new("/home/me/a-b_c.d~")),
+            "/home/me/a-b_c.d~"
+        );
+        assert_eq!(
+            encode_path(Path::new("/tmp/with space/100%")),
+            "/tmp/with%20space/100%25"
+        );
+        assert_eq!(encode_path(Path::new("dir/é#?")), "dir/%C3%A9%23%3F");
+    }
+}
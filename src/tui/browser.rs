@@ -0,0 +1,483 @@
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    terminal::{
+        self, Clear, ClearType, DisableLineWrap, EnableLineWrap, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+use crate::{
+    config::{Config, SortBy},
+    file_system::entry::{sort_entries, FsEntry},
+    output::chart::make_chart,
+    some_or,
+    stats::ScanStats,
+    tui::actions::{measure, Action, TrashDir},
+    units::system::UnitSystem,
+};
+
+// Lines taken up by the header and the key help, around the list of entries
+const HEADER_LINES: u16 = 3;
+const FOOTER_LINES: u16 = 1;
+
+const HELP: &str = "↑/↓ move   Enter open   Backspace up   n/s/t/a sort   r reverse   \
+                    Space mark   d delete   m move to trash   q quit";
+
+/// Browses an already scanned tree, keeping the terminal in raw mode on an alternate
+/// screen until the user quits.
+pub fn browse(entries: Vec<FsEntry>, resolved_dir: &str, config: &Config) -> anyhow::Result<()> {
+    let mut browser = Browser::new(entries, resolved_dir, config);
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, Hide, DisableLineWrap)?;
+
+    let result = browser.run(&mut stdout);
+
+    // The terminal is restored even when drawing failed, so that the error can be read
+    execute!(stdout, EnableLineWrap, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    result
+}
+
+struct Browser {
+    entries: Vec<FsEntry>,
+    root: PathBuf,
+    unit_system: UnitSystem,
+    sort_by: SortBy,
+    reverse: bool,
+    max_bar_width: u32,
+    // Unset for snapshots, whose entries may no longer exist
+    editable: bool,
+    disk_usage: bool,
+    trash: Option<TrashDir>,
+    // Names of the directories descended into, starting from the root
+    path: Vec<OsString>,
+    selected: usize,
+    // Index of the first entry shown, once the list no longer fits the screen
+    offset: usize,
+    // Names of the marked entries in the current directory
+    marked: HashSet<OsString>,
+    // Set while waiting for the user to confirm an action
+    prompt: Option<Action>,
+    // Shown in place of the key help until the next key press
+    status: Option<String>,
+}
+
+impl Browser {
+    fn new(mut entries: Vec<FsEntry>, resolved_dir: &str, config: &Config) -> Self {
+        // Browsing without a sort order is hard to follow, so the biggest entries go first
+        let sort_by = config.sort_by.unwrap_or(SortBy::Size);
+        sort_entries(&mut entries, &sort_by, config.reverse);
+
+        Self {
+            entries,
+            root: PathBuf::from(resolved_dir),
+            unit_system: config.unit_system,
+            sort_by,
+            reverse: config.reverse,
+            max_bar_width: config.max_bar_width,
+            editable: config.load.is_none(),
+            disk_usage: config.disk_usage,
+            trash: match &config.trash_dir {
+                Some(dir) => Some(TrashDir::Custom(dir.clone())),
+                None => TrashDir::desktop(),
+            },
+            path: Vec::new(),
+            selected: 0,
+            offset: 0,
+            marked: HashSet::new(),
+            prompt: None,
+            status: None,
+        }
+    }
+
+    fn run(&mut self, stdout: &mut impl Write) -> anyhow::Result<()> {
+        loop {
+            self.draw(stdout)?;
+
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            if !self.handle_key(key) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns false once the user asks to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let page = self.list_height();
+        let len = self.current().len();
+        self.status = None;
+
+        if let Some(action) = self.prompt.take() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.apply(action),
+                _ => self.status = Some("Cancelled".to_string()),
+            }
+            self.selected = self.selected.min(self.current().len().saturating_sub(1));
+            return true;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected += 1,
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+            KeyCode::PageDown => self.selected += page,
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = len.saturating_sub(1),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.descend(),
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => self.ascend(),
+            KeyCode::Char('n') => self.resort(SortBy::Name, self.reverse),
+            KeyCode::Char('s') => self.resort(SortBy::Size, self.reverse),
+            KeyCode::Char('t') => self.resort(SortBy::Type, self.reverse),
+            KeyCode::Char('a') => self.resort(SortBy::Modified, self.reverse),
+            KeyCode::Char('r') => self.resort(self.sort_by, !self.reverse),
+            KeyCode::Char(' ') => self.toggle_mark(),
+            KeyCode::Char('d') => self.confirm(Action::Delete),
+            KeyCode::Char('m') => self.confirm(Action::Trash),
+            _ => {}
+        }
+
+        self.selected = self.selected.min(self.current().len().saturating_sub(1));
+        true
+    }
+
+    /// The entries of the directory currently being browsed.
+    fn current(&self) -> &[FsEntry] {
+        let mut entries = self.entries.as_slice();
+        for name in &self.path {
+            entries = match entries.iter().find(|fse| fse.name() == name) {
+                Some(fse) => fse.children().map(Vec::as_slice).unwrap_or_default(),
+                None => return &[],
+            };
+        }
+        entries
+    }
+
+    fn current_mut(&mut self) -> Option<&mut Vec<FsEntry>> {
+        let mut entries = &mut self.entries;
+        for name in &self.path {
+            entries = entries
+                .iter_mut()
+                .find(|fse| fse.name() == name)?
+                .children_mut()?;
+        }
+        Some(entries)
+    }
+
+    fn current_dir(&self) -> PathBuf {
+        self.path
+            .iter()
+            .fold(self.root.clone(), |dir, name| dir.join(name))
+    }
+
+    fn descend(&mut self) {
+        let Some(fse) = self.current().get(self.selected) else {
+            return;
+        };
+        if fse.children().is_none() {
+            return;
+        }
+
+        self.path.push(fse.name().clone());
+        self.selected = 0;
+        self.offset = 0;
+        self.marked.clear();
+    }
+
+    fn ascend(&mut self) {
+        let Some(name) = self.path.pop() else {
+            return;
+        };
+
+        // Come back to the directory that was just left
+        self.selected = self.position_of(&name);
+        self.offset = 0;
+        self.marked.clear();
+    }
+
+    /// Sorts the whole tree, so that every level shows the same order, and keeps the
+    /// selected entry selected.
+    fn resort(&mut self, sort_by: SortBy, reverse: bool) {
+        let selected = self
+            .current()
+            .get(self.selected)
+            .map(|fse| fse.name().clone());
+
+        self.sort_by = sort_by;
+        self.reverse = reverse;
+        sort_entries(&mut self.entries, &self.sort_by, self.reverse);
+
+        if let Some(name) = selected {
+            self.selected = self.position_of(&name);
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        let Some(name) = self
+            .current()
+            .get(self.selected)
+            .map(|fse| fse.name().clone())
+        else {
+            return;
+        };
+        if !self.marked.remove(&name) {
+            self.marked.insert(name);
+        }
+        self.selected += 1;
+    }
+
+    /// The marked entries, or the selected one when nothing is marked.
+    fn targets(&self) -> Vec<&FsEntry> {
+        let entries = self.current();
+        match self.marked.is_empty() {
+            true => entries.get(self.selected).into_iter().collect(),
+            false => entries
+                .iter()
+                .filter(|fse| self.marked.contains(fse.name()))
+                .collect(),
+        }
+    }
+
+    fn confirm(&mut self, action: Action) {
+        if !self.editable {
+            self.status = Some("Entries loaded from a snapshot can't be changed".to_string());
+            return;
+        }
+
+        let dir = self.current_dir();
+        let targets = self.targets();
+        let what = match targets.as_slice() {
+            [] => return,
+            [fse] => format!("'{}'", fse.name_str()),
+            targets => format!("{} entries", targets.len()),
+        };
+
+        // What is on disk now is measured again, since the scan may have left some of it out
+        let scanned: u64 = targets.iter().map(|fse| fse.size().unwrap_or(0)).sum();
+        let mut reclaimed = 0;
+        for fse in &targets {
+            match measure(&dir.join(fse.name()), self.disk_usage) {
+                Ok(size) => reclaimed += size,
+                Err(err) => {
+                    self.status = Some(format!(
+                        "Can't {} {what}: {err}",
+                        action.verb().to_lowercase()
+                    ));
+                    return;
+                }
+            }
+        }
+
+        let mut status = match action.frees_space() {
+            true => format!(
+                "{} {what}, reclaiming {}",
+                action.verb(),
+                self.format_bytes(reclaimed)
+            ),
+            false => format!(
+                "{} {what}, {} to be freed once the trash is emptied",
+                action.verb(),
+                self.format_bytes(reclaimed)
+            ),
+        };
+        if reclaimed > scanned {
+            status.push_str(&format!(
+                " (only {} of it was scanned)",
+                self.format_bytes(scanned)
+            ));
+        }
+        status.push_str("? [y/N]");
+        self.status = Some(status);
+        self.prompt = Some(action);
+    }
+
+    fn apply(&mut self, action: Action) {
+        let dir = self.current_dir();
+        let mut done = Vec::new();
+        let mut reclaimed = 0;
+        let mut errors = Vec::new();
+        for fse in self.targets() {
+            let path = dir.join(fse.name());
+            // Measuring again right before removing catches mounts that appeared since
+            let result = measure(&path, self.disk_usage)
+                .and_then(|size| action.apply(&path, self.trash.as_ref()).map(|()| size));
+            match result {
+                Ok(size) => {
+                    done.push(fse.name().clone());
+                    reclaimed += size;
+                }
+                Err(err) => errors.push(err),
+            }
+        }
+
+        self.remove(&done);
+        self.marked.clear();
+
+        let mut status = format!(
+            "{} {} {}, {} {}",
+            action.past_tense(),
+            done.len(),
+            if done.len() == 1 { "entry" } else { "entries" },
+            self.format_bytes(reclaimed),
+            match action.frees_space() {
+                true => "reclaimed",
+                false => "to be freed once the trash is emptied",
+            },
+        );
+        if let Some(err) = errors.first() {
+            status.push_str(&format!(" ({} failed: {err})", errors.len()));
+        }
+        self.status = Some(status);
+    }
+
+    /// Takes entries out of the current directory, and their sizes out of every directory
+    /// above it, so that the totals stay right without rescanning.
+    fn remove(&mut self, names: &[OsString]) {
+        let Some(entries) = self.current_mut() else {
+            return;
+        };
+        let (removed, kept): (Vec<FsEntry>, Vec<FsEntry>) = std::mem::take(entries)
+            .into_iter()
+            .partition(|fse| names.contains(fse.name()));
+        *entries = kept;
+
+        let mut entries = &mut self.entries;
+        for name in &self.path {
+            let Some(fse) = entries.iter_mut().find(|fse| fse.name() == name) else {
+                break;
+            };
+            for r in &removed {
+                fse.subtract(r);
+            }
+            entries = some_or!(fse.children_mut(), break);
+        }
+    }
+
+    fn format_bytes(&self, bytes: u64) -> String {
+        match self.unit_system {
+            UnitSystem::SI | UnitSystem::Binary => self.unit_system.format(bytes),
+            UnitSystem::Raw | UnitSystem::Lines | UnitSystem::Count => format!("{bytes} bytes"),
+        }
+    }
+
+    fn position_of(&self, name: &OsString) -> usize {
+        self.current()
+            .iter()
+            .position(|fse| fse.name() == name)
+            .unwrap_or(0)
+    }
+
+    fn list_height(&self) -> usize {
+        let (_, rows) = terminal::size().unwrap_or((80, 24));
+        rows.saturating_sub(HEADER_LINES + FOOTER_LINES).max(1) as usize
+    }
+
+    fn draw(&mut self, stdout: &mut impl Write) -> anyhow::Result<()> {
+        let (cols, _) = terminal::size()?;
+        let height = self.list_height();
+
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + height {
+            self.offset = self.selected + 1 - height;
+        }
+
+        let entries = self.current();
+        let mut stats = ScanStats::default();
+        for fse in entries {
+            stats.apply_entry(fse);
+        }
+
+        let total = stats.total_units(&self.unit_system);
+        let sort_name = match self.sort_by {
+            SortBy::Name => "name",
+            SortBy::Size => "size",
+            SortBy::Type => "type",
+            SortBy::Modified => "mtime",
+        };
+
+        // Leave room for the name, the brackets and the size column
+        let (max_units, max_digits) = stats.bar_scale(&self.unit_system);
+        let bar_width = self
+            .max_bar_width
+            .min((cols as usize).saturating_sub(stats.max_name_len + max_digits + 16) as u32);
+        let chart = make_chart(
+            entries,
+            &self.unit_system,
+            max_units,
+            max_digits,
+            stats.max_name_len,
+            bar_width,
+        );
+
+        let dir = self.current_dir();
+
+        queue!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+        write!(
+            stdout,
+            "{}\r\n{}\r\n\r\n",
+            console::style(dir.to_string_lossy()).bold(),
+            console::style(format!(
+                "Total: {}   Items: {}   Sorted by {}{}",
+                self.unit_system.format(total),
+                entries.len(),
+                sort_name,
+                if self.reverse { " (reversed)" } else { "" },
+            ))
+            .dim(),
+        )?;
+
+        if entries.is_empty() {
+            write!(stdout, "  {}\r\n", console::style("(empty)").dim())?;
+        }
+        for (i, (fse, line)) in entries
+            .iter()
+            .zip(chart.lines())
+            .enumerate()
+            .skip(self.offset)
+            .take(height)
+        {
+            let mark = if self.marked.contains(fse.name()) {
+                '*'
+            } else {
+                ' '
+            };
+            if i == self.selected {
+                let line = console::strip_ansi_codes(line);
+                write!(
+                    stdout,
+                    "{}\r\n",
+                    console::style(format!(">{mark}{line}")).reverse()
+                )?;
+            } else {
+                write!(stdout, " {mark}{line}\r\n")?;
+            }
+        }
+
+        let (_, rows) = terminal::size()?;
+        queue!(stdout, MoveTo(0, rows.saturating_sub(FOOTER_LINES)))?;
+        match &self.status {
+            Some(status) => write!(stdout, "{}", console::style(status).bold())?,
+            None => write!(stdout, "{}", console::style(HELP).dim())?,
+        }
+
+        stdout.flush()?;
+        Ok(())
+    }
+}
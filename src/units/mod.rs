@@ -1,3 +1,4 @@
+pub mod parse;
 pub mod system;
 
 const B: &str = "B";
@@ -0,0 +1,206 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use crate::units::{system::UnitSystem, B};
+
+/// Parses a size such as `1024`, `500KB` or `1.5GiB` into bytes.
+///
+/// Suffixes are the units of `UnitSystem::SI_UNITS` (powers of 1000) and
+/// `UnitSystem::BINARY_UNITS` (powers of 1024). A bare prefix like `M` is rejected,
+/// since it could mean either.
+pub fn parse_size(input: &str) -> anyhow::Result<u64> {
+    let s = input.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, suffix) = (&s[..split], s[split..].trim_start());
+
+    if number.is_empty() {
+        return Err(anyhow!("invalid size '{}': expected a number", input));
+    }
+    let value: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("invalid size '{}': '{}' is not a number", input, number))?;
+
+    let multiplier = unit_multiplier(suffix).map_err(|msg| {
+        anyhow!(
+            "invalid size '{}': {} (expected a unit like B, KB, MB, KiB, MiB)",
+            input,
+            msg
+        )
+    })?;
+
+    // Whole numbers are kept exact instead of going through f64
+    if let Ok(n) = number.parse::<u64>() {
+        return n
+            .checked_mul(multiplier)
+            .ok_or_else(|| anyhow!("invalid size '{}': value is too large", input));
+    }
+
+    if multiplier == 1 && value.fract() != 0.0 {
+        return Err(anyhow!(
+            "invalid size '{}': a number of bytes cannot be fractional",
+            input
+        ));
+    }
+
+    let bytes = (value * multiplier as f64).round();
+    // u64::MAX rounds up to 2^64 as a float, which is already out of range
+    if bytes >= u64::MAX as f64 {
+        return Err(anyhow!("invalid size '{}': value is too large", input));
+    }
+
+    Ok(bytes as u64)
+}
+
+fn unit_multiplier(suffix: &str) -> Result<u64, String> {
+    if suffix.is_empty() || suffix == B {
+        return Ok(1);
+    }
+
+    let find_exp = |units: &[&str], suffix: &str| {
+        units
+            .iter()
+            .position(|unit| unit.eq_ignore_ascii_case(suffix))
+            .map(|i| i as u32)
+    };
+
+    // A lowercase 'b' usually means bits, which sizes are never measured in
+    if suffix.ends_with('b') {
+        return Err(format!(
+            "unit '{}' is ambiguous, use an uppercase 'B' for bytes",
+            suffix
+        ));
+    }
+
+    if let Some(exp) = find_exp(&UnitSystem::SI_UNITS, suffix) {
+        return Ok(1000u64.pow(exp));
+    }
+    if let Some(exp) = find_exp(&UnitSystem::BINARY_UNITS, suffix) {
+        return Ok(1024u64.pow(exp));
+    }
+
+    // A bare prefix like "M" would silently mean different sizes to different readers
+    if let Some(exp) = find_exp(&UnitSystem::SI_UNITS, &format!("{suffix}B")) {
+        return Err(format!(
+            "unit '{}' is ambiguous, use '{}' ({}) or '{}' ({})",
+            suffix,
+            UnitSystem::SI_UNITS[exp as usize],
+            1000u64.pow(exp),
+            UnitSystem::BINARY_UNITS[exp as usize],
+            1024u64.pow(exp),
+        ));
+    }
+
+    Err(format!("unknown unit '{}'", suffix))
+}
+
+/// Parses an age such as `90d`, `12h` or `2w` into a duration.
+///
+/// Suffixes are `s`, `m` (minutes), `h`, `d`, `w` and `y` (365 days).
+pub fn parse_age(input: &str) -> anyhow::Result<Duration> {
+    let s = input.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, suffix) = (&s[..split], s[split..].trim_start());
+
+    if number.is_empty() {
+        return Err(anyhow!("invalid age '{}': expected a number", input));
+    }
+    let value: u64 = number
+        .parse()
+        .map_err(|_| anyhow!("invalid age '{}': '{}' is not a number", input, number))?;
+
+    let multiplier = match suffix.to_ascii_lowercase().as_str() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        "" => {
+            return Err(anyhow!(
+                "invalid age '{}': missing unit (expected one of s, m, h, d, w, y)",
+                input
+            ))
+        }
+        _ => {
+            return Err(anyhow!(
+                "invalid age '{}': unknown unit '{}' (expected one of s, m, h, d, w, y)",
+                input,
+                suffix
+            ))
+        }
+    };
+
+    value
+        .checked_mul(multiplier)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("invalid age '{}': value is too large", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size(" 500KB ").unwrap(), 500_000);
+        assert_eq!(parse_size("10 MiB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("1.5GiB").unwrap(), 1_610_612_736);
+        assert_eq!(parse_size("0.5kB").unwrap(), 500);
+        assert_eq!(parse_size("1B").unwrap(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        for input in [
+            "", "KB", "-5", "-5KB", "1.5", "1..5MB", "10kb", "10Mb", "10XB",
+        ] {
+            assert!(parse_size(input).is_err(), "{input:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn rejects_bare_prefixes() {
+        let err = parse_size("10M").unwrap_err().to_string();
+        assert!(err.contains("'MB'") && err.contains("'MiB'"), "{err}");
+        assert!(parse_size("1k").is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_sizes() {
+        assert_eq!(parse_size("15EiB").unwrap(), 15 << 60);
+        assert_eq!(parse_size("18EB").unwrap(), 18_000_000_000_000_000_000);
+        assert!(parse_size("16EiB").is_err());
+        assert!(parse_size("15.99EiB").is_ok());
+        assert!(parse_size("16.0EiB").is_err());
+        assert!(parse_size("20EB").is_err());
+        assert!(parse_size("99999999999999999999").is_err());
+        assert!(parse_size("100000000000000000000.5KB").is_err());
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(
+            parse_age("90d").unwrap(),
+            Duration::from_secs(90 * 24 * 60 * 60)
+        );
+        assert_eq!(parse_age("12H").unwrap(), Duration::from_secs(12 * 60 * 60));
+        assert_eq!(parse_age("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(
+            parse_age("2 w").unwrap(),
+            Duration::from_secs(14 * 24 * 60 * 60)
+        );
+        assert_eq!(parse_age("0s").unwrap(), Duration::ZERO);
+    }
+
+    #[test]
+    fn rejects_invalid_ages() {
+        for input in ["", "d", "90", "-1d", "1.5d", "3x", "99999999999999999999y"] {
+            assert!(parse_age(input).is_err(), "{input:?} should be rejected");
+        }
+        assert!(parse_age(&format!("{}y", u64::MAX / 1000)).is_err());
+    }
+}
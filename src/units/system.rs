@@ -6,12 +6,24 @@ pub enum UnitSystem {
     SI,
     Binary,
     Lines,
+    Count,
 }
 
 impl UnitSystem {
     pub const SI_UNITS: [&str; 7] = [B, KB, MB, GB, TB, PB, EB];
     pub const BINARY_UNITS: [&str; 7] = [B, KIB, MIB, GIB, TIB, PIB, EIB];
     pub const LINES: &str = "lines";
+    pub const ENTRIES: &str = "entries";
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Raw => "raw",
+            Self::SI => "si",
+            Self::Binary => "binary",
+            Self::Lines => "lines",
+            Self::Count => "count",
+        }
+    }
 
     pub fn format(&self, units: u64) -> String {
         match self {
@@ -19,14 +31,21 @@ impl UnitSystem {
             Self::SI => Self::format_bytes(units, 1000, Self::SI_UNITS),
             Self::Binary => Self::format_bytes(units, 1024, Self::BINARY_UNITS),
             Self::Lines => format!("{units} {}", Self::LINES),
+            Self::Count => format!("{units} {}", Self::ENTRIES),
+        }
+    }
+
+    /// How much of an entry there is in these units: its size, lines or entry count.
+    pub fn units_of(&self, fse: &FsEntry) -> Option<u64> {
+        match self {
+            Self::Raw | Self::SI | Self::Binary => fse.size(),
+            Self::Lines => fse.lines(),
+            Self::Count => Some(fse.count()),
         }
     }
 
     pub fn format_entry(&self, fse: &FsEntry) -> String {
-        self.format(match self {
-            Self::Raw | Self::SI | Self::Binary => fse.size().unwrap_or(0),
-            Self::Lines => fse.lines().unwrap_or(0),
-        })
+        self.format(self.units_of(fse).unwrap_or(0))
     }
 
     fn format_bytes(bytes: u64, base: u32, units: [&str; 7]) -> String {
@@ -1,31 +1,85 @@
-use std::sync::{Condvar, Mutex};
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+};
 
-pub struct Semaphore {
-    max_threads: usize,
-    mu: Mutex<usize>,
+/// A fixed-size pool of worker threads sharing one job stack. Workers may push
+/// new jobs while running, and all of them exit once no job is queued or running.
+pub struct WorkPool<J> {
+    state: Mutex<PoolState<J>>,
     cvar: Condvar,
 }
 
-impl Semaphore {
-    pub fn new(max_threads: usize) -> Self {
-        Self {
-            max_threads,
-            mu: Mutex::new(0usize),
+struct PoolState<J> {
+    jobs: Vec<J>,
+    // Queued plus running jobs
+    pending: usize,
+}
+
+impl<J: Send + 'static> WorkPool<J> {
+    pub fn new(mut jobs: Vec<J>) -> Arc<Self> {
+        // Jobs are popped from the back, so the first ones given should be started first
+        jobs.reverse();
+        Arc::new(Self {
+            state: Mutex::new(PoolState {
+                pending: jobs.len(),
+                jobs,
+            }),
             cvar: Condvar::new(),
-        }
+        })
+    }
+
+    pub fn push(&self, job: J) {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.push(job);
+        state.pending += 1;
+        self.cvar.notify_one();
+    }
+
+    pub fn spawn<F>(self: &Arc<Self>, threads: usize, work: F) -> Vec<JoinHandle<()>>
+    where
+        F: Fn(J, &WorkPool<J>) + Send + Sync + 'static,
+    {
+        let work = Arc::new(work);
+
+        (0..threads)
+            .map(|_| {
+                let pool = self.clone();
+                let work = work.clone();
+
+                thread::spawn(move || {
+                    while let Some(job) = pool.next() {
+                        // Marks the job as finished even if it panics, so other workers don't wait forever
+                        let _guard = FinishGuard(&pool);
+                        work(job, &pool);
+                    }
+                })
+            })
+            .collect()
     }
 
-    pub fn lock(&self) {
-        let mut count = self.mu.lock().unwrap();
-        while *count >= self.max_threads {
-            count = self.cvar.wait(count).unwrap();
+    fn next(&self) -> Option<J> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(job) = state.jobs.pop() {
+                return Some(job);
+            }
+            if state.pending == 0 {
+                return None;
+            }
+            state = self.cvar.wait(state).unwrap();
         }
-        *count += 1;
     }
+}
 
-    pub fn unlock(&self) {
-        let mut count = self.mu.lock().unwrap();
-        *count -= 1;
-        self.cvar.notify_one();
+struct FinishGuard<'a, J>(&'a WorkPool<J>);
+
+impl<J> Drop for FinishGuard<'_, J> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.pending -= 1;
+        if state.pending == 0 {
+            self.0.cvar.notify_all();
+        }
     }
 }